@@ -0,0 +1,139 @@
+use std::io;
+
+use camino::Utf8Path;
+
+#[cfg(target_os = "linux")]
+use crate::xattr;
+
+/// The access control list of a file or directory, see [`Utf8PathExt::acl`](crate::Utf8PathExt::acl).
+///
+/// On Linux these are the POSIX ACLs, including the default ACL of a directory, and
+/// on Windows the DACL of the security descriptor. The ACL is kept in the native
+/// format, so it can only be applied on the platform it was read on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Acl {
+    #[cfg(target_os = "linux")]
+    access: Option<Vec<u8>>,
+    #[cfg(target_os = "linux")]
+    default: Option<Vec<u8>>,
+    /// A self-relative security descriptor, in a buffer aligned for it.
+    #[cfg(windows)]
+    descriptor: Vec<u64>,
+}
+
+impl Acl {
+    /// Whether there is no ACL beyond the permission bits. Always false on Windows.
+    pub fn is_empty(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        return self.access.is_none() && self.default.is_none();
+        #[cfg(not(target_os = "linux"))]
+        return false;
+    }
+}
+
+pub(crate) fn read_acl(path: &Utf8Path) -> io::Result<Acl> {
+    sys_read_acl(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read ACL of {path} due to: {e}"),
+        )
+    })
+}
+
+pub(crate) fn write_acl(path: &Utf8Path, acl: &Acl) -> io::Result<()> {
+    sys_write_acl(path, acl)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not set ACL of {path} due to: {e}")))
+}
+
+#[cfg(target_os = "linux")]
+const ACCESS_ATTR: &str = "system.posix_acl_access";
+#[cfg(target_os = "linux")]
+const DEFAULT_ATTR: &str = "system.posix_acl_default";
+
+#[cfg(target_os = "linux")]
+fn sys_read_acl(path: &Utf8Path) -> io::Result<Acl> {
+    Ok(Acl {
+        access: xattr::get(path, ACCESS_ATTR)?,
+        default: xattr::get(path, DEFAULT_ATTR)?,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn sys_write_acl(path: &Utf8Path, acl: &Acl) -> io::Result<()> {
+    xattr::set(path, ACCESS_ATTR, acl.access.as_deref())?;
+    xattr::set(path, DEFAULT_ATTR, acl.default.as_deref())
+}
+
+#[cfg(windows)]
+fn sys_read_acl(path: &Utf8Path) -> io::Result<Acl> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Security::{GetFileSecurityW, DACL_SECURITY_INFORMATION};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut needed = 0u32;
+    // SAFETY: `wide` is nul terminated and a null buffer only queries the length.
+    unsafe {
+        GetFileSecurityW(
+            wide.as_ptr(),
+            DACL_SECURITY_INFORMATION,
+            std::ptr::null_mut(),
+            0,
+            &mut needed,
+        )
+    };
+    if needed == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut descriptor = vec![0u64; (needed as usize).div_ceil(8)];
+    // SAFETY: the buffer is valid for at least `needed` bytes.
+    let ok = unsafe {
+        GetFileSecurityW(
+            wide.as_ptr(),
+            DACL_SECURITY_INFORMATION,
+            descriptor.as_mut_ptr().cast(),
+            needed,
+            &mut needed,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Acl { descriptor })
+}
+
+#[cfg(windows)]
+fn sys_write_acl(path: &Utf8Path, acl: &Acl) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Security::{SetFileSecurityW, DACL_SECURITY_INFORMATION};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: `wide` is nul terminated and the descriptor was returned by `GetFileSecurityW`,
+    // which is only read.
+    let ok = unsafe {
+        SetFileSecurityW(
+            wide.as_ptr(),
+            DACL_SECURITY_INFORMATION,
+            acl.descriptor.as_ptr() as *mut _,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn sys_read_acl(_path: &Utf8Path) -> io::Result<Acl> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ACLs are only supported on Linux and Windows",
+    ))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn sys_write_acl(_path: &Utf8Path, _acl: &Acl) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ACLs are only supported on Linux and Windows",
+    ))
+}
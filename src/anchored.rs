@@ -0,0 +1,154 @@
+use std::{fmt, io};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+
+/// A path within a root directory, kept as the root and a relative path that can't
+/// be absolute or climb out of the root with `..`, e.g. for "path within project"
+/// values that are passed around between APIs.
+///
+/// The relative path is normalized by dropping `.` components, and it is empty for
+/// the root itself. It displays as the full path.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let src = AnchoredPath::new("/project", "./src").unwrap();
+/// let main = src.join("bin/main.rs").unwrap();
+/// assert_eq!(main.rel(), "src/bin/main.rs");
+/// assert_eq!(main.to_string(), "/project/src/bin/main.rs");
+/// assert!(main.join("../../../etc/passwd").is_err());
+/// assert!(AnchoredPath::new("/project", "/etc/passwd").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RawAnchoredPath")
+)]
+pub struct AnchoredPath {
+    root: Utf8PathBuf,
+    rel: Utf8PathBuf,
+}
+
+impl AnchoredPath {
+    /// The relative path within the root. Fails if it is absolute or contains `..`.
+    pub fn new<R: Into<Utf8PathBuf>, P: AsRef<Utf8Path>>(root: R, rel: P) -> io::Result<Self> {
+        let root = root.into();
+        let rel = normalized_rel(&root, rel.as_ref())?;
+        Ok(Self { root, rel })
+    }
+
+    /// The root itself.
+    pub fn root_only<R: Into<Utf8PathBuf>>(root: R) -> Self {
+        Self {
+            root: root.into(),
+            rel: Utf8PathBuf::new(),
+        }
+    }
+
+    /// The path below the root. Fails if the path doesn't start with the root.
+    pub fn strip_root<R: Into<Utf8PathBuf>, P: AsRef<Utf8Path>>(
+        root: R,
+        path: P,
+    ) -> io::Result<Self> {
+        let root = root.into();
+        let path = path.as_ref();
+        let rel = path.strip_prefix(&root).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Could not anchor {path} at {root} as it is outside of it"),
+            )
+        })?;
+        Self::new(root, rel)
+    }
+
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    /// The path relative to the root, which is empty for the root itself.
+    pub fn rel(&self) -> &Utf8Path {
+        &self.rel
+    }
+
+    /// The root joined with the relative path.
+    pub fn to_path(&self) -> Utf8PathBuf {
+        if self.rel.as_str().is_empty() {
+            return self.root.clone();
+        }
+        self.root.join(&self.rel)
+    }
+
+    /// Append a relative path. Fails if it is absolute or contains `..`.
+    pub fn join<P: AsRef<Utf8Path>>(&self, rel: P) -> io::Result<Self> {
+        let rel = normalized_rel(&self.root, &self.rel.join(rel.as_ref()))?;
+        Ok(Self {
+            root: self.root.clone(),
+            rel,
+        })
+    }
+
+    /// The parent within the root, or `None` for the root itself.
+    pub fn parent(&self) -> Option<Self> {
+        let parent = self.rel.parent()?;
+        Some(Self {
+            root: self.root.clone(),
+            rel: parent.to_path_buf(),
+        })
+    }
+
+    /// The same relative path within another root.
+    pub fn with_root<R: Into<Utf8PathBuf>>(&self, root: R) -> Self {
+        Self {
+            root: root.into(),
+            rel: self.rel.clone(),
+        }
+    }
+}
+
+impl fmt::Display for AnchoredPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_path().fmt(f)
+    }
+}
+
+impl From<AnchoredPath> for Utf8PathBuf {
+    fn from(path: AnchoredPath) -> Self {
+        path.to_path()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawAnchoredPath {
+    root: Utf8PathBuf,
+    rel: Utf8PathBuf,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawAnchoredPath> for AnchoredPath {
+    type Error = io::Error;
+
+    fn try_from(raw: RawAnchoredPath) -> io::Result<Self> {
+        Self::new(raw.root, raw.rel)
+    }
+}
+
+fn normalized_rel(root: &Utf8Path, rel: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    let mut normalized = Utf8PathBuf::new();
+    for c in rel.components() {
+        match c {
+            Utf8Component::Normal(name) => normalized.push(name),
+            Utf8Component::CurDir => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "Could not anchor {rel} at {root} as it isn't a relative path within it"
+                    ),
+                ))
+            }
+        }
+    }
+    Ok(normalized)
+}
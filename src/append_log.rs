@@ -0,0 +1,332 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Write},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, ByteSize, Utf8PathExt};
+
+/// The largest record, so that a corrupt length can't make reading allocate gigabytes.
+const MAX_RECORD_LEN: usize = 1 << 30;
+
+/// How the records of an [`AppendLog`] are separated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Each record is prefixed by its length as a little endian `u32`. Records can be
+    /// at most 1 GiB.
+    #[default]
+    LengthPrefixed,
+    /// Each record is followed by a newline. Records can't contain newlines.
+    Lines,
+}
+
+/// When an [`AppendLog`] flushes its records to disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Leave it to the operating system. Records may be lost on a power failure.
+    #[default]
+    Never,
+    /// Sync after every record.
+    EveryRecord,
+    /// Sync after every n records.
+    Every(usize),
+}
+
+/// A file that records are only ever appended to, e.g. for audit logs or job queues.
+///
+/// A record that was only partially written, e.g. because of a crash, is skipped
+/// when reading the records, and cut off before the next record is appended so
+/// that it can't corrupt the records after it.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let path = dir.join("jobs.log");
+/// let mut log = AppendLog::open(path).unwrap().sync_policy(SyncPolicy::EveryRecord);
+/// log.append_record("first").unwrap();
+/// log.append_record(b"second").unwrap();
+///
+/// let records: Vec<Vec<u8>> = log.records().unwrap().collect::<Result<_, _>>().unwrap();
+/// assert_eq!(records, [b"first".to_vec(), b"second".to_vec()]);
+/// ```
+#[derive(Debug)]
+pub struct AppendLog {
+    path: Utf8PathBuf,
+    file: File,
+    framing: Framing,
+    sync_policy: SyncPolicy,
+    unsynced: usize,
+    /// Whether a partly written record at the end was cut off.
+    repaired: bool,
+}
+
+impl AppendLog {
+    /// Open or create the log file, creating the parent directories as needed.
+    pub fn open<P: Into<Utf8PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            file,
+            framing: Framing::default(),
+            sync_policy: SyncPolicy::default(),
+            unsynced: 0,
+            repaired: false,
+        })
+    }
+
+    /// Set the framing of the records. It has to be the same every time the log is opened.
+    ///
+    /// The file is only repaired when the first record is appended, as the framing is
+    /// needed to find where the last complete record ends.
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
+        self
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Append a record, syncing according to the [`SyncPolicy`].
+    pub fn append_record<B: AsRef<[u8]>>(&mut self, record: B) -> io::Result<()> {
+        if !self.repaired {
+            self.cut_off_partial_record()?;
+            self.repaired = true;
+        }
+        let record = record.as_ref();
+        let mut buf = Vec::with_capacity(record.len() + 4);
+        match self.framing {
+            Framing::LengthPrefixed => {
+                let len = u32::try_from(record.len())
+                    .ok()
+                    .filter(|_| record.len() <= MAX_RECORD_LEN);
+                let len = len.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Could not append a record of {} bytes to {}",
+                            record.len(),
+                            self.path
+                        ),
+                    )
+                })?;
+                buf.extend(len.to_le_bytes());
+                buf.extend(record);
+            }
+            Framing::Lines => {
+                if record.contains(&b'\n') {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("Could not append a record with a newline to {}", self.path),
+                    ));
+                }
+                buf.extend(record);
+                buf.push(b'\n');
+            }
+        }
+        // A single write keeps the record together when several processes append.
+        self.file.write_all(&buf).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not append to {} due to: {e}", self.path),
+            )
+        })?;
+
+        self.unsynced += 1;
+        let sync = match self.sync_policy {
+            SyncPolicy::Never => false,
+            SyncPolicy::EveryRecord => true,
+            SyncPolicy::Every(n) => self.unsynced >= n,
+        };
+        if sync {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Flush the appended records to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not sync {} due to: {e}", self.path),
+            )
+        })?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Iterate over the records in the log, from the oldest.
+    pub fn records(&self) -> io::Result<Records> {
+        let err = |e: io::Error| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not open {} due to: {e}", self.path),
+            )
+        };
+        let file = File::open(&self.path).map_err(err)?;
+        let len = file.metadata().map_err(err)?.len();
+        Ok(Records {
+            path: self.path.clone(),
+            reader: BufReader::new(file),
+            framing: self.framing,
+            len,
+            pos: 0,
+            done: false,
+        })
+    }
+
+    /// Truncate the file to the end of the last complete record.
+    fn cut_off_partial_record(&mut self) -> io::Result<()> {
+        let mut records = self.records()?;
+        for record in records.by_ref() {
+            match record {
+                Ok(_) => {}
+                // A corrupt length, after which nothing can be read.
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if records.pos < records.len {
+            self.file.set_len(records.pos).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not truncate {} due to: {e}", self.path),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Move the current log aside and continue with an empty one. Returns the path
+    /// the records were moved to.
+    ///
+    /// The previous log is moved to `<path>.1`, and earlier rotated logs are renamed
+    /// from `<path>.1` to `<path>.2` and so on.
+    pub fn rotate(&mut self) -> io::Result<Utf8PathBuf> {
+        if self.unsynced > 0 && self.sync_policy != SyncPolicy::Never {
+            self.sync()?;
+        }
+        let rotated = |n: usize| Utf8PathBuf::from(format!("{}.{n}", self.path));
+        let mut last = 0;
+        while rotated(last + 1).exists() {
+            last += 1;
+        }
+        for n in (1..=last).rev() {
+            fs_rename(&rotated(n), &rotated(n + 1))?;
+        }
+        fs_rename(&self.path, &rotated(1))?;
+        self.file = open_append(&self.path)?;
+        self.unsynced = 0;
+        self.repaired = true;
+        Ok(rotated(1))
+    }
+}
+
+fn open_append(path: &Utf8Path) -> io::Result<File> {
+    if let Some(parent) = path.parent() {
+        parent.mkdirs()?;
+    }
+    File::options()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not open {path} due to: {e}")))
+}
+
+/// Iterator over the records of an [`AppendLog`], see [`AppendLog::records`].
+pub struct Records {
+    path: Utf8PathBuf,
+    reader: BufReader<File>,
+    framing: Framing,
+    /// The length of the file when the iteration started.
+    len: u64,
+    /// The end of the last complete record that was read.
+    pos: u64,
+    /// Set after the last record or an error, as the position after it is unknown.
+    done: bool,
+}
+
+impl Records {
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let remaining = self.len - self.pos;
+        match self.framing {
+            Framing::LengthPrefixed => {
+                let mut len = [0; 4];
+                if remaining < 4 || !read_full(&mut self.reader, &mut len)? {
+                    return Ok(None);
+                }
+                let len = u32::from_le_bytes(len) as usize;
+                if len > MAX_RECORD_LEN {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "a record of {} is larger than {}",
+                            ByteSize(len as u64),
+                            ByteSize(MAX_RECORD_LEN as u64)
+                        ),
+                    ));
+                }
+                if len as u64 > remaining - 4 {
+                    // A record that was not completely written.
+                    return Ok(None);
+                }
+                let mut record = vec![0; len];
+                if !read_full(&mut self.reader, &mut record)? {
+                    return Ok(None);
+                }
+                self.pos += 4 + len as u64;
+                Ok(Some(record))
+            }
+            Framing::Lines => {
+                let mut record = Vec::new();
+                self.reader.read_until(b'\n', &mut record)?;
+                // A record without the newline was not completely written.
+                if record.pop() != Some(b'\n') || self.pos + record.len() as u64 >= self.len {
+                    return Ok(None);
+                }
+                self.pos += record.len() as u64 + 1;
+                Ok(Some(record))
+            }
+        }
+    }
+}
+
+impl Iterator for Records {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+        record.map(|record| {
+            record.map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not read {} due to: {e}", self.path),
+                )
+            })
+        })
+    }
+}
+
+/// Fill the buffer, returning false if the end of the file was reached first.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Utf8PathExt;
+
+/// Options for [`Utf8PathExt::tar_to_writer_with`].
+///
+/// The default options are the same as [`Utf8PathExt::tar_to_writer`].
+#[derive(Debug, Clone, Default)]
+pub struct TarOptions {
+    deterministic: bool,
+}
+
+impl TarOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make archives of identical trees byte-identical, e.g. for caching or signing.
+    ///
+    /// The entries are sorted by path, timestamps are set to a fixed date, owners
+    /// to root and permissions to `755` for directories and executables and to
+    /// `644` for other files.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+}
+
+pub(crate) fn tar_to_writer<W: Write>(
+    dir: &Utf8Path,
+    writer: W,
+    opts: &TarOptions,
+) -> io::Result<W> {
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not write archive of {dir} due to: {e}"),
+        )
+    };
+    dir.assert_dir()?;
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    if opts.deterministic {
+        builder.mode(tar::HeaderMode::Deterministic);
+    }
+
+    let base = dir.to_path_buf();
+    let ls = dir
+        .ls()
+        .recurse_if(move |rel| !base.join(rel).is_symlink())
+        .depth_first()
+        .relative_paths();
+    let mut paths: Vec<Utf8PathBuf> = ls.try_iter().collect::<io::Result<_>>()?;
+    if opts.deterministic {
+        paths.sort();
+    }
+    for rel in paths {
+        builder
+            .append_path_with_name(dir.join(&rel), &rel)
+            .map_err(err)?;
+    }
+    builder.into_inner().map_err(err)
+}
+
+pub(crate) fn untar_from_reader<R: Read>(dir: &Utf8Path, reader: R) -> io::Result<()> {
+    dir.mkdirs()?;
+    // Entries with paths outside of `dir` are skipped by unpack.
+    tar::Archive::new(reader).unpack(dir).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not extract archive to {dir} due to: {e}"),
+        )
+    })
+}
+
+/// An index of the entries in a tar archive, to extract single entries without
+/// reading or extracting the rest of the archive.
+///
+/// Opening the index only reads the entry headers and seeks over their contents,
+/// so it is fast even for large archives. Compressed archives can't be indexed as
+/// they can't be seeked in.
+///
+/// ```
+/// use camino_fs::*;
+/// use std::fs::File;
+///
+/// let src = TestDir::new().with_file("docs/readme.md", "hello");
+/// let archive = TestDir::new();
+/// let path = archive.join("docs.tar");
+/// src.path().tar_to_writer(File::create(&path).unwrap()).unwrap();
+///
+/// let index = ArchiveIndex::open(&path).unwrap();
+/// assert!(index.list().iter().any(|entry| entry.path() == "docs/readme.md"));
+/// index.extract_entry("docs/readme.md", archive.join("readme.md")).unwrap();
+/// archive.assert_file("readme.md", "hello");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ArchiveIndex {
+    path: Utf8PathBuf,
+    entries: Vec<ArchiveEntry>,
+}
+
+/// An entry in an [`ArchiveIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    path: Utf8PathBuf,
+    size: u64,
+    kind: ArchiveEntryKind,
+    /// Where the content starts in the archive.
+    position: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveEntryKind {
+    File,
+    Dir,
+    Other,
+}
+
+impl ArchiveEntry {
+    /// The path in the archive, without a leading `./`.
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// The length of the content, which is 0 for anything but files.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == ArchiveEntryKind::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == ArchiveEntryKind::Dir
+    }
+}
+
+impl ArchiveIndex {
+    /// Read the entry headers of the uncompressed tar archive at `path`.
+    pub fn open<P: Into<Utf8PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let err = |e: io::Error| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not read archive {path} due to: {e}"),
+            )
+        };
+        let file = File::open(&path).map_err(err)?;
+        let mut archive = tar::Archive::new(file);
+        let mut entries = Vec::new();
+        for entry in archive.entries_with_seek().map_err(err)? {
+            let entry = entry.map_err(err)?;
+            let entry_path = entry.path().map_err(err)?;
+            let entry_path = Utf8Path::from_path(&entry_path).ok_or_else(|| {
+                io::Error::other(format!(
+                    "Could not read archive {path} as the entry {entry_path:?} is not valid UTF-8"
+                ))
+            })?;
+            let entry_type = entry.header().entry_type();
+            let kind = if entry_type.is_file() {
+                ArchiveEntryKind::File
+            } else if entry_type.is_dir() {
+                ArchiveEntryKind::Dir
+            } else {
+                ArchiveEntryKind::Other
+            };
+            entries.push(ArchiveEntry {
+                path: entry_path
+                    .strip_prefix(".")
+                    .unwrap_or(entry_path)
+                    .to_path_buf(),
+                size: if kind == ArchiveEntryKind::File {
+                    entry.size()
+                } else {
+                    0
+                },
+                kind,
+                position: entry.raw_file_position(),
+            });
+        }
+        Ok(Self { path, entries })
+    }
+
+    /// The entries in the order they are in the archive.
+    pub fn list(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// Write the content of the file at `name` in the archive to `dest`, creating its
+    /// parent directories. For a directory entry the directory is created.
+    ///
+    /// Fails with `NotFound` if there is no such entry and with `Unsupported` for
+    /// entries like symlinks that are neither files nor directories.
+    pub fn extract_entry<N: AsRef<Utf8Path>, P: AsRef<Utf8Path>>(
+        &self,
+        name: N,
+        dest: P,
+    ) -> io::Result<()> {
+        let (name, dest) = (name.as_ref(), dest.as_ref());
+        let name = name.strip_prefix(".").unwrap_or(name);
+        let entry = self
+            .entries
+            .iter()
+            .rev()
+            .find(|entry| entry.path == name)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("Could not find {name} in archive {}", self.path),
+                )
+            })?;
+        match entry.kind {
+            ArchiveEntryKind::Dir => dest.mkdirs(),
+            ArchiveEntryKind::Other => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Could not extract {name} from archive {} as it is not a file or directory",
+                    self.path
+                ),
+            )),
+            ArchiveEntryKind::File => {
+                if let Some(parent) = dest.parent() {
+                    parent.mkdirs()?;
+                }
+                let err = |e: io::Error| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("Could not extract {name} to {dest} due to: {e}"),
+                    )
+                };
+                let mut archive = File::open(&self.path).map_err(err)?;
+                archive.seek(SeekFrom::Start(entry.position)).map_err(err)?;
+                let mut out = File::create(dest).map_err(err)?;
+                let copied = io::copy(&mut archive.take(entry.size), &mut out).map_err(err)?;
+                if copied < entry.size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "Could not extract {name} as archive {} is truncated",
+                            self.path
+                        ),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+}
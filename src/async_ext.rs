@@ -0,0 +1,144 @@
+use std::{future::Future, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{Spawned, Utf8PathExt};
+
+/// Async versions of the [`Utf8PathExt`] methods using `tokio::fs`.
+///
+/// The methods have an `_async` suffix so they don't clash with [`Utf8PathExt`] when
+/// both traits are in scope. Recursive operations and moves run the blocking
+/// implementation on tokio's blocking thread pool, so they behave exactly like the
+/// sync ones.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+/// let dir = TestDir::new();
+/// let path = dir.join("hello.txt");
+/// path.write_async("hello").await.unwrap();
+/// assert_eq!(path.read_string_async().await.unwrap(), "hello");
+/// # });
+/// ```
+pub trait AsyncUtf8PathExt {
+    /// See [`Utf8PathExt::read_bytes`].
+    fn read_bytes_async(&self) -> impl Future<Output = io::Result<Vec<u8>>> + Send;
+
+    /// See [`Utf8PathExt::read_string`].
+    fn read_string_async(&self) -> impl Future<Output = io::Result<String>> + Send;
+
+    /// See [`Utf8PathExt::write`].
+    fn write_async<B: AsRef<[u8]> + Send>(
+        &self,
+        buf: B,
+    ) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// See [`Utf8PathExt::mkdir`].
+    fn mkdir_async(&self) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// See [`Utf8PathExt::mkdirs`].
+    fn mkdirs_async(&self) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// See [`Utf8PathExt::rm`].
+    fn rm_async(&self) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// See [`Utf8PathExt::cp`].
+    fn cp_async<P: Into<Utf8PathBuf>>(&self, to: P) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// See [`Utf8PathExt::mv`].
+    fn mv_async<P: Into<Utf8PathBuf>>(&self, to: P) -> impl Future<Output = io::Result<()>> + Send;
+
+    /// Returns true if the path exists. Errors other than the path not existing,
+    /// e.g. missing permissions, are returned.
+    fn exists_async(&self) -> impl Future<Output = io::Result<bool>> + Send;
+}
+
+impl AsyncUtf8PathExt for Utf8Path {
+    fn read_bytes_async(&self) -> impl Future<Output = io::Result<Vec<u8>>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            tokio::fs::read(&path)
+                .await
+                .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))
+        }
+    }
+
+    fn read_string_async(&self) -> impl Future<Output = io::Result<String>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))
+        }
+    }
+
+    fn write_async<B: AsRef<[u8]> + Send>(
+        &self,
+        buf: B,
+    ) -> impl Future<Output = io::Result<()>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            if let Some(parent) = path.parent() {
+                parent.mkdirs_async().await?;
+            }
+            tokio::fs::write(&path, buf).await.map_err(|e| {
+                io::Error::new(e.kind(), format!("Could not write to {path} due to: {e}"))
+            })
+        }
+    }
+
+    fn mkdir_async(&self) -> impl Future<Output = io::Result<()>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            match tokio::fs::create_dir(&path).await {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => Err(io::Error::new(
+                    e.kind(),
+                    format!("Could not create directory {path} due to: {e}"),
+                )),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    fn mkdirs_async(&self) -> impl Future<Output = io::Result<()>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            tokio::fs::create_dir_all(&path).await.map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not create directories for {path} due to: {e}"),
+                )
+            })
+        }
+    }
+
+    fn rm_async(&self) -> impl Future<Output = io::Result<()>> + Send {
+        let path = self.to_path_buf();
+        // Spawned when first polled, like the other methods that are lazy.
+        async move { Spawned::new(move || path.rm()).await }
+    }
+
+    fn cp_async<P: Into<Utf8PathBuf>>(&self, to: P) -> impl Future<Output = io::Result<()>> + Send {
+        let (path, to) = (self.to_path_buf(), to.into());
+        async move { Spawned::new(move || path.cp(to)).await }
+    }
+
+    fn mv_async<P: Into<Utf8PathBuf>>(&self, to: P) -> impl Future<Output = io::Result<()>> + Send {
+        let (path, to) = (self.to_path_buf(), to.into());
+        // Blocking, to fall back to copying across file systems like the sync version.
+        async move { Spawned::new(move || path.mv(to)).await }
+    }
+
+    fn exists_async(&self) -> impl Future<Output = io::Result<bool>> + Send {
+        let path = self.to_path_buf();
+        async move {
+            tokio::fs::try_exists(&path).await.map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not check if {path} exists due to: {e}"),
+                )
+            })
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    fs::{FileTimes, Metadata},
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, link, natural::natural_cmp, Utf8PathExt};
+
+pub(crate) fn backup_snapshot(
+    dir: &Utf8Path,
+    snapshots_root: &Utf8Path,
+) -> io::Result<Utf8PathBuf> {
+    dir.assert_dir()?;
+    snapshots_root.mkdirs()?;
+    let previous = latest_snapshot(snapshots_root)?;
+
+    let name = unique_snapshot_name(snapshots_root, SystemTime::now());
+    // Built under a hidden name and renamed when complete, so an interrupted
+    // backup is never taken for a snapshot.
+    let partial = snapshots_root.join(format!(".{name}.partial"));
+    let snapshot = snapshots_root.join(name);
+    let res = fill_snapshot(dir, &partial, previous.as_deref())
+        .and_then(|_| fs_rename(&partial, &snapshot));
+    if let Err(e) = res {
+        let _ = partial.rm();
+        return Err(e);
+    }
+    Ok(snapshot)
+}
+
+fn fill_snapshot(dir: &Utf8Path, dest: &Utf8Path, previous: Option<&Utf8Path>) -> io::Result<()> {
+    fs_create_dir(dest)?;
+    let base = dir.to_path_buf();
+    let ls = dir
+        .ls()
+        .recurse_if(move |rel| !base.join(rel).is_symlink())
+        .relative_paths();
+    for entry in ls.try_entries() {
+        let entry = entry.map_err(|e| {
+            io::Error::new(e.kind(), format!("Could not back up {dir} due to: {e}"))
+        })?;
+        let rel = entry.path();
+        let (src, dest) = (dir.join(rel), dest.join(rel));
+        if entry.is_symlink() {
+            link::copy_link(&src, &dest)?;
+        } else if entry.is_dir() {
+            fs_create_dir(&dest)?;
+        } else if entry.is_file() {
+            let unchanged = previous
+                .map(|previous| previous.join(rel))
+                .filter(|previous| is_unchanged(entry.metadata(), previous));
+            // Linking fails when the file has reached the maximum number of links.
+            if unchanged.is_none_or(|previous| fs_hard_link(&previous, &dest).is_err()) {
+                fs_copy(&src, &dest)?;
+                // The modification time is what the next snapshot compares.
+                let times = FileTimes::new().set_modified(entry.metadata().modified()?);
+                fs_set_times(&dest, times)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file in the previous snapshot has the same length and modification time.
+fn is_unchanged(md: &Metadata, previous: &Utf8Path) -> bool {
+    let Ok(previous) = previous.symlink_metadata() else {
+        return false;
+    };
+    previous.is_file()
+        && previous.len() == md.len()
+        && previous
+            .modified()
+            .ok()
+            .is_some_and(|t| Some(t) == md.modified().ok())
+}
+
+/// The most recent complete snapshot in the directory.
+fn latest_snapshot(snapshots_root: &Utf8Path) -> io::Result<Option<Utf8PathBuf>> {
+    let mut names: Vec<String> = snapshots_root
+        .ls()
+        .dirs()
+        .relative_paths()
+        .try_iter()
+        .map(|rel| rel.map(String::from))
+        .filter(|name| !name.as_ref().is_ok_and(|name| name.starts_with('.')))
+        .collect::<io::Result<_>>()?;
+    names.sort_by(|a, b| natural_cmp(a, b));
+    Ok(names.pop().map(|name| snapshots_root.join(name)))
+}
+
+/// A name like `2024-05-01T13-45-10Z` that sorts by time, with a `-2`, `-3`, ...
+/// suffix when there already is a snapshot with the same name.
+pub(crate) fn unique_snapshot_name(snapshots_root: &Utf8Path, now: SystemTime) -> String {
+    let name = utc_timestamp(now);
+    let mut candidate = name.clone();
+    let mut n = 1;
+    while snapshots_root.join(&candidate).exists() {
+        n += 1;
+        candidate = format!("{name}-{n}");
+    }
+    candidate
+}
+
+/// The time in UTC as `YYYY-MM-DDTHH-MM-SSZ`, using `-` instead of `:` as that is
+/// not allowed in names on Windows.
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, rem) = (secs / 86400, secs % 86400);
+    // Converts days since 1970-01-01 to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}-{:02}-{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
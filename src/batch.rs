@@ -0,0 +1,85 @@
+use std::{
+    io,
+    num::NonZeroUsize,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::fs::fs_read;
+
+/// Read many files, e.g. config or asset files at startup, returning the content
+/// or error for each path in the given order.
+///
+/// With `threads` greater than one, the files are read by that many threads.
+///
+/// ```
+/// use camino_fs::*;
+/// use std::num::NonZeroUsize;
+///
+/// let results = read_many(["Cargo.toml", "missing.toml"], NonZeroUsize::new(2).unwrap());
+/// assert!(results[0].1.is_ok());
+/// assert!(results[1].1.is_err());
+/// ```
+pub fn read_many<I, P>(paths: I, threads: NonZeroUsize) -> Vec<(Utf8PathBuf, io::Result<Vec<u8>>)>
+where
+    I: IntoIterator<Item = P>,
+    P: Into<Utf8PathBuf>,
+{
+    let paths: Vec<Utf8PathBuf> = paths.into_iter().map(Into::into).collect();
+    let contents = map_parallel(&paths, threads, fs_read);
+    paths.into_iter().zip(contents).collect()
+}
+
+/// Check which of the paths exist, e.g. when probing many candidate locations.
+/// The result has one entry per path, in the given order.
+///
+/// With `threads` greater than one, the paths are checked by that many threads.
+///
+/// ```
+/// use camino_fs::*;
+/// use std::num::NonZeroUsize;
+///
+/// let found = exists_many(&["Cargo.toml", "missing.toml"], NonZeroUsize::MIN);
+/// assert_eq!(found, [true, false]);
+/// ```
+pub fn exists_many<P: AsRef<Utf8Path> + Sync>(paths: &[P], threads: NonZeroUsize) -> Vec<bool> {
+    map_parallel(paths, threads, Utf8Path::exists)
+}
+
+/// Apply `f` to all paths using up to `threads` threads, keeping the order.
+pub(crate) fn map_parallel<P, T, F>(paths: &[P], threads: NonZeroUsize, f: F) -> Vec<T>
+where
+    P: AsRef<Utf8Path> + Sync,
+    T: Send,
+    F: Fn(&Utf8Path) -> T + Sync,
+{
+    let threads = threads.get().min(paths.len());
+    if threads <= 1 {
+        return paths.iter().map(|p| f(p.as_ref())).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else {
+                            break done;
+                        };
+                        done.push((i, f(path.as_ref())));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
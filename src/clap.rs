@@ -0,0 +1,83 @@
+//! Value parsers for [clap](https://docs.rs/clap) arguments that produce [`Utf8PathBuf`]s.
+//!
+//! ```
+//! use camino_fs::{clap::existing_dir_parser, Utf8PathBuf};
+//! use clap::{Arg, Command};
+//!
+//! let matches = Command::new("app")
+//!     .arg(Arg::new("dir").value_parser(existing_dir_parser()))
+//!     .get_matches_from(["app", "src"]);
+//! let dir: &Utf8PathBuf = matches.get_one("dir").unwrap();
+//! assert_eq!(dir, "src");
+//! ```
+
+use std::io;
+
+use ::clap::builder::TypedValueParser;
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Utf8PathExt;
+
+/// Accepts any UTF-8 path.
+pub fn path_parser() -> impl TypedValueParser<Value = Utf8PathBuf> {
+    |s: &str| -> io::Result<Utf8PathBuf> { Ok(Utf8PathBuf::from(s)) }
+}
+
+/// Accepts a path to an existing file or directory.
+pub fn existing_path_parser() -> impl TypedValueParser<Value = Utf8PathBuf> {
+    |s: &str| -> io::Result<Utf8PathBuf> {
+        let path = Utf8PathBuf::from(s);
+        path.assert_exists()?;
+        Ok(path)
+    }
+}
+
+/// Accepts a path to an existing file.
+pub fn existing_file_parser() -> impl TypedValueParser<Value = Utf8PathBuf> {
+    |s: &str| -> io::Result<Utf8PathBuf> {
+        let path = Utf8PathBuf::from(s);
+        path.assert_exists()?;
+        path.assert_file()?;
+        Ok(path)
+    }
+}
+
+/// Accepts a path to an existing directory.
+pub fn existing_dir_parser() -> impl TypedValueParser<Value = Utf8PathBuf> {
+    |s: &str| -> io::Result<Utf8PathBuf> {
+        let path = Utf8PathBuf::from(s);
+        path.assert_exists()?;
+        path.assert_dir()?;
+        Ok(path)
+    }
+}
+
+/// Accepts a path where a file can be created, i.e. it is not an existing directory
+/// and its closest existing ancestor is a directory. Missing parent directories are
+/// accepted as [`Utf8PathExt::write`] creates them.
+pub fn creatable_path_parser() -> impl TypedValueParser<Value = Utf8PathBuf> {
+    |s: &str| -> io::Result<Utf8PathBuf> {
+        let path = Utf8PathBuf::from(s);
+        if path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path \"{path}\" is an existing directory!"),
+            ));
+        }
+        let ancestor = path
+            .ancestors()
+            .skip(1)
+            .find(|p| p.as_str().is_empty() || p.exists())
+            .map(|p| {
+                if p.as_str().is_empty() {
+                    Utf8Path::new(".")
+                } else {
+                    p
+                }
+            });
+        if let Some(ancestor) = ancestor {
+            ancestor.assert_dir()?;
+        }
+        Ok(path)
+    }
+}
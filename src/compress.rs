@@ -0,0 +1,189 @@
+use std::{
+    fs::{File, FileTimes},
+    io::{self, Read, Write},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, temp::sibling_temp_path, Utf8PathExt};
+
+/// A compression format, see [`Utf8PathExt::read_bytes_auto`](crate::Utf8PathExt::read_bytes_auto).
+///
+/// Each codec requires the feature of the same name. Without it, using the codec
+/// fails with an `Unsupported` error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Codec {
+    /// gzip, with the `.gz` extension.
+    Gzip,
+    /// Zstandard, with the `.zst` extension.
+    Zstd,
+}
+
+impl Codec {
+    /// The codec of compressed data, detected by the magic bytes at the start.
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Codec::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Codec::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// The file extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gz",
+            Codec::Zstd => "zst",
+        }
+    }
+
+    fn feature(self) -> &'static str {
+        match self {
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn unsupported(self, path: &Utf8Path) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Could not process {path} as {self:?} requires the {} feature",
+                self.feature()
+            ),
+        )
+    }
+
+    /// A reader that decompresses what is read from `reader`.
+    pub(crate) fn decoder<'a, R: Read + 'a>(
+        self,
+        path: &Utf8Path,
+        reader: R,
+    ) -> io::Result<Box<dyn Read + 'a>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => Ok(Box::new(zstd::Decoder::new(reader)?)),
+            #[allow(unreachable_patterns)]
+            _ => {
+                drop(reader);
+                Err(self.unsupported(path))
+            }
+        }
+    }
+
+    /// Compress everything from `reader` into `writer`, returning the writer.
+    fn encode<W: Write>(self, path: &Utf8Path, reader: &mut File, writer: W) -> io::Result<W> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Codec::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+                io::copy(reader, &mut encoder)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "zstd")]
+            Codec::Zstd => {
+                let mut encoder = zstd::Encoder::new(writer, 0)?;
+                io::copy(reader, &mut encoder)?;
+                encoder.finish()
+            }
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = reader;
+                drop(writer);
+                Err(self.unsupported(path))
+            }
+        }
+    }
+}
+
+pub(crate) fn read_bytes_auto(path: &Utf8Path) -> io::Result<Vec<u8>> {
+    let bytes = fs_read(path)?;
+    let Some(codec) = Codec::detect(&bytes) else {
+        return Ok(bytes);
+    };
+    let mut decompressed = Vec::new();
+    codec
+        .decoder(path, bytes.as_slice())?
+        .read_to_end(&mut decompressed)
+        .map_err(|e| {
+            io::Error::new(e.kind(), format!("Could not decompress {path} due to: {e}"))
+        })?;
+    Ok(decompressed)
+}
+
+pub(crate) fn compress_in_place(path: &Utf8Path, codec: Codec) -> io::Result<Utf8PathBuf> {
+    let dest = Utf8PathBuf::from(format!("{path}.{}", codec.extension()));
+    replace_with(path, &dest, "compress", |mut src, out| {
+        codec.encode(path, &mut src, out)
+    })?;
+    Ok(dest)
+}
+
+pub(crate) fn decompress_in_place(path: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    let mut magic = Vec::new();
+    File::open(path)
+        .and_then(|file| file.take(4).read_to_end(&mut magic))
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))?;
+    let Some(codec) = Codec::detect(&magic) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Could not decompress {path} as it is not compressed"),
+        ));
+    };
+    if path.extension() != Some(codec.extension()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Could not decompress {path} as it doesn't have the .{} extension",
+                codec.extension()
+            ),
+        ));
+    }
+    let dest = path.with_extension("");
+    replace_with(path, &dest, "decompress", |src, mut out| {
+        io::copy(&mut codec.decoder(path, src)?, &mut out)?;
+        Ok(out)
+    })?;
+    Ok(dest)
+}
+
+/// Write `dest` from the contents of `path` through a temporary file, and remove
+/// `path` once `dest` is complete. The permissions and times of `path` are kept.
+fn replace_with<F>(path: &Utf8Path, dest: &Utf8Path, action: &str, write: F) -> io::Result<()>
+where
+    F: FnOnce(File, File) -> io::Result<File>,
+{
+    if dest.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Could not {action} {path} as {dest} already exists"),
+        ));
+    }
+    let md = fs_metadata(path)?;
+    let src = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not open {path} due to: {e}")))?;
+    let temp = sibling_temp_path(dest, action);
+
+    let result = File::create(&temp)
+        .and_then(|out| write(src, out))
+        .and_then(|out| out.sync_all())
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not {action} {path} due to: {e}")))
+        .and_then(|_| {
+            let times = FileTimes::new()
+                .set_accessed(md.accessed()?)
+                .set_modified(md.modified()?);
+            fs_set_times(&temp, times)
+        })
+        .and_then(|_| fs_set_permissions(&temp, md.permissions()))
+        .and_then(|_| fs_rename(&temp, dest));
+    if let Err(e) = result {
+        let _ = temp.rm();
+        return Err(e);
+    }
+    fs_remove_file(path)
+}
@@ -0,0 +1,454 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{FileTimes, Metadata},
+    io,
+    sync::Arc,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    fs::*,
+    mount::{device_id, metadata_device_id},
+    temp::sibling_temp_path,
+    vfs, ExistingDirPolicy, FileKind, OverwritePolicy, SpecialFilePolicy, StdFs, Utf8PathExt, Vfs,
+};
+
+/// Options for [`Utf8PathExt::cp_with`].
+///
+/// The default options are the same as [`Utf8PathExt::cp`].
+#[derive(Debug, Clone, Default)]
+pub struct CpOptions {
+    free_space_margin: Option<u64>,
+    follow_symlinks: bool,
+    existing_dir: ExistingDirPolicy,
+    same_filesystem_only: bool,
+    special_files: SpecialFilePolicy,
+    overwrite: OverwritePolicy,
+    filter: Option<Filter>,
+    preserve_permissions: bool,
+    preserve_timestamps: bool,
+    #[cfg(unix)]
+    preserve_owner: bool,
+    #[cfg(feature = "acl")]
+    preserve_acls: bool,
+    #[cfg(target_os = "linux")]
+    preserve_security_xattrs: bool,
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    preserve_xattrs: bool,
+}
+
+#[derive(Clone)]
+struct Filter(Arc<dyn Fn(&Utf8Path) -> bool + Send + Sync>);
+
+impl fmt::Debug for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Filter")
+    }
+}
+
+impl CpOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Before copying, check that the destination has room for the source
+    /// plus `margin` bytes, failing upfront instead of running out of space
+    /// halfway through the copy.
+    pub fn check_free_space(mut self, margin: u64) -> Self {
+        self.free_space_margin = Some(margin);
+        self
+    }
+
+    /// Copy what symlinks and junctions point to instead of recreating the links.
+    ///
+    /// By default the links themselves are copied, so a link to a directory outside
+    /// the source is never copied into the destination.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// # #[cfg(unix)] {
+    /// let outside = TestDir::new().with_file("data.txt", "data");
+    /// let src = TestDir::new().with_file("file.txt", "");
+    /// std::os::unix::fs::symlink(outside.path(), src.join("link")).unwrap();
+    ///
+    /// let dest = TestDir::new();
+    /// src.path().cp(dest.join("linked")).unwrap();
+    /// assert!(dest.join("linked/link").is_symlink());
+    /// dest.join("linked").rm().unwrap();
+    /// outside.assert_file("data.txt", "data");
+    ///
+    /// let options = CpOptions::default().follow_symlinks(true);
+    /// src.path().cp_with(dest.join("followed"), &options).unwrap();
+    /// assert!(!dest.join("followed/link").is_symlink());
+    /// dest.assert_file("followed/link/data.txt", "data");
+    /// outside.assert_tree(&["data.txt"]);
+    /// # }
+    /// ```
+    pub fn follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// How to handle copying a directory to a directory that already exists.
+    /// The default is to merge the contents.
+    pub fn existing_dir(mut self, policy: ExistingDirPolicy) -> Self {
+        self.existing_dir = policy;
+        self
+    }
+
+    /// Don't descend into directories on another file system than the source, like
+    /// `cp -x`. The mount points are created as empty directories.
+    ///
+    /// Only supported on Unix, elsewhere this has no effect.
+    pub fn same_filesystem_only(mut self) -> Self {
+        self.same_filesystem_only = true;
+        self
+    }
+
+    /// How to handle sockets, FIFOs and devices. The default is to fail, as copying
+    /// their bytes would hang or copy a whole device.
+    pub fn special_files(mut self, policy: SpecialFilePolicy) -> Self {
+        self.special_files = policy;
+        self
+    }
+
+    /// How to handle files that already exist in the destination. The default is
+    /// to overwrite them.
+    pub fn overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = policy;
+        self
+    }
+
+    /// Only copy the entries for which `filter` returns true. It is given the path
+    /// relative to the copied directory, and a directory that is left out is left
+    /// out with all its contents.
+    ///
+    /// Has no effect when copying a single file.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Utf8Path) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Filter(Arc::new(filter)));
+        self
+    }
+
+    /// Give the copied directories the same permissions as the source.
+    ///
+    /// Copied files always get the permissions of the source.
+    pub fn preserve_permissions(mut self) -> Self {
+        self.preserve_permissions = true;
+        self
+    }
+
+    /// Give the copied files and directories the same access and modification
+    /// times as the source.
+    pub fn preserve_timestamps(mut self) -> Self {
+        self.preserve_timestamps = true;
+        self
+    }
+
+    /// Give the copied files and directories the same owner and group as the source.
+    ///
+    /// Changing the owner usually requires root privileges.
+    #[cfg(unix)]
+    pub fn preserve_owner(mut self) -> Self {
+        self.preserve_owner = true;
+        self
+    }
+
+    /// Give the copied files and directories the same access control lists as the
+    /// source, see [`Utf8PathExt::acl`].
+    #[cfg(feature = "acl")]
+    pub fn preserve_acls(mut self) -> Self {
+        self.preserve_acls = true;
+        self
+    }
+
+    /// Copy the extended attributes in the `security` namespace, such as SELinux
+    /// contexts and IMA signatures, see [`Utf8PathExt::security_context`].
+    ///
+    /// Setting them usually requires root privileges.
+    #[cfg(target_os = "linux")]
+    pub fn preserve_security_xattrs(mut self) -> Self {
+        self.preserve_security_xattrs = true;
+        self
+    }
+
+    /// Copy the extended attributes that don't need privileges to set, which are those
+    /// in the `user` namespace on Linux and all of them on macOS, see
+    /// [`Utf8PathExt::xattr_get`].
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    pub fn preserve_xattrs(mut self) -> Self {
+        self.preserve_xattrs = true;
+        self
+    }
+}
+
+pub(crate) fn cp(
+    fs: &dyn Vfs,
+    from: &Utf8Path,
+    dest: &Utf8Path,
+    opts: &CpOptions,
+) -> io::Result<()> {
+    let copy_as_link = !opts.follow_symlinks && is_link(fs, from);
+    // Like the shell `cp`, a file copied to an existing directory is placed inside it.
+    let in_dir;
+    let dest = match from.file_name() {
+        Some(name) if (copy_as_link || !is_dir(fs, from)) && is_dir(fs, dest) => {
+            in_dir = dest.join(name);
+            &in_dir
+        }
+        _ => dest,
+    };
+
+    if copy_as_link {
+        return copy_link(fs, from, dest, opts);
+    }
+    let from_md = fs.metadata(from)?;
+
+    if let Some(margin) = opts.free_space_margin {
+        dest.ensure_free_space(tree_len(fs, from)? + margin)?;
+    }
+
+    if !from_md.is_dir() {
+        return copy_file(fs, from, dest, opts);
+    }
+
+    if fs.exists(dest) {
+        prepare_existing_dir(fs, from, dest, opts.existing_dir)?;
+    }
+    fs.create_dir_all(dest)?;
+
+    let mut entries: VecDeque<Utf8PathBuf> = fs.read_dir(from)?.into();
+    let device = device_id(from).filter(|_| opts.same_filesystem_only);
+    // Directory attributes are applied last, as copying into them changes their
+    // modification time and read-only directories can't be copied into.
+    let mut dirs = vec![(from.to_path_buf(), dest.to_path_buf())];
+
+    while let Some(src_path) = entries.pop_front() {
+        let rel_path = src_path.strip_prefix(from).unwrap();
+        if let Some(filter) = &opts.filter {
+            if !(filter.0)(rel_path) {
+                continue;
+            }
+        }
+        let dest_path = dest.join(rel_path);
+        // An entry that can't be read is left to `copy_file` to report.
+        let md = fs.symlink_metadata(&src_path).ok();
+        let other_device = device.is_some() && md.as_ref().and_then(metadata_device_id) != device;
+        let is_link = md.as_ref().is_some_and(|md| md.is_symlink());
+
+        if !opts.follow_symlinks && is_link {
+            copy_link(fs, &src_path, &dest_path, opts)?;
+        } else if md.as_ref().is_some_and(|md| md.is_dir()) || is_link && is_dir(fs, &src_path) {
+            if !other_device {
+                entries.extend(fs.read_dir(&src_path)?);
+            }
+            if !is_dir(fs, &dest_path) {
+                fs.create_dir(&dest_path)?;
+            }
+            dirs.push((src_path, dest_path));
+        } else {
+            copy_file(fs, &src_path, &dest_path, opts)?;
+        }
+    }
+
+    for (src_dir, dest_dir) in dirs.iter().rev() {
+        copy_attributes(fs, src_dir, dest_dir, opts)?;
+    }
+    Ok(())
+}
+
+fn copy_file(fs: &dyn Vfs, from: &Utf8Path, dest: &Utf8Path, opts: &CpOptions) -> io::Result<()> {
+    let md = fs.metadata(from)?;
+    if is_special(from, &md, opts)? || !should_write(fs, from, &md, dest, opts)? {
+        return Ok(());
+    }
+    fs.copy(from, dest)?;
+    copy_attributes(fs, from, dest, opts)
+}
+
+fn copy_link(fs: &dyn Vfs, from: &Utf8Path, dest: &Utf8Path, opts: &CpOptions) -> io::Result<()> {
+    let md = fs.symlink_metadata(from)?;
+    if !should_write(fs, from, &md, dest, opts)? {
+        return Ok(());
+    }
+    // A link can't be created over an existing file, but a directory is never replaced.
+    if fs.symlink_metadata(dest).is_ok_and(|md| !md.is_dir()) {
+        vfs::rm(fs, dest)?;
+    }
+    fs.copy_link(from, dest)
+}
+
+/// Whether `src` should be written to `dest` according to the overwrite policy.
+fn should_write(
+    fs: &dyn Vfs,
+    src: &Utf8Path,
+    src_md: &Metadata,
+    dest: &Utf8Path,
+    opts: &CpOptions,
+) -> io::Result<bool> {
+    let Ok(dest_md) = fs.symlink_metadata(dest) else {
+        return Ok(true);
+    };
+    match opts.overwrite {
+        OverwritePolicy::Overwrite => Ok(true),
+        OverwritePolicy::Skip => Ok(false),
+        OverwritePolicy::Error => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Could not copy {src} to {dest} as it already exists"),
+        )),
+        OverwritePolicy::IfNewer => Ok(src_md.modified()? > dest_md.modified()?),
+    }
+}
+
+/// Move by copying and removing, for when a rename isn't possible across file systems.
+///
+/// The copy is made next to the destination and renamed into place once it is
+/// complete, so a failed move never leaves a partial destination behind.
+pub(crate) fn move_across_devices(from: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    let temp = sibling_temp_path(dest, "moving");
+    let opts = CpOptions::new()
+        .preserve_permissions()
+        .preserve_timestamps();
+    if let Err(e) = cp(&StdFs, from, &temp, &opts).and_then(|_| fs_rename(&temp, dest)) {
+        let _ = temp.rm();
+        return Err(e);
+    }
+    from.rm()
+}
+
+fn prepare_existing_dir(
+    fs: &dyn Vfs,
+    from: &Utf8Path,
+    dest: &Utf8Path,
+    policy: ExistingDirPolicy,
+) -> io::Result<()> {
+    match policy {
+        ExistingDirPolicy::Merge => Ok(()),
+        ExistingDirPolicy::Replace => {
+            // Removing a directory that contains the source would remove the source.
+            if fs_canonicalize(from)?.starts_with(fs_canonicalize(dest)?) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Could not replace {dest} as it contains the source {from}"),
+                ));
+            }
+            vfs::rm(fs, dest)
+        }
+        ExistingDirPolicy::Error => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Could not copy {from} to {dest} as it already exists"),
+        )),
+    }
+}
+
+/// Whether the file should be skipped as a special file, or an error if it may not be copied.
+fn is_special(path: &Utf8Path, md: &Metadata, opts: &CpOptions) -> io::Result<bool> {
+    let kind = FileKind::from(md.file_type());
+    if !kind.is_special() {
+        return Ok(false);
+    }
+    match opts.special_files {
+        SpecialFilePolicy::Skip => Ok(true),
+        SpecialFilePolicy::Error => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Could not copy {path} as it is a {kind}"),
+        )),
+    }
+}
+
+/// Whether the path is a symlink or junction.
+fn is_link(fs: &dyn Vfs, path: &Utf8Path) -> bool {
+    fs.symlink_metadata(path).is_ok_and(|md| md.is_symlink())
+}
+
+fn is_dir(fs: &dyn Vfs, path: &Utf8Path) -> bool {
+    fs.metadata(path).is_ok_and(|md| md.is_dir())
+}
+
+/// Apply the attributes selected in the options from `src` to the already copied `dest`.
+fn copy_attributes(
+    fs: &dyn Vfs,
+    src: &Utf8Path,
+    dest: &Utf8Path,
+    opts: &CpOptions,
+) -> io::Result<()> {
+    #[cfg(unix)]
+    let preserve_owner = opts.preserve_owner;
+    #[cfg(not(unix))]
+    let preserve_owner = false;
+    #[cfg(feature = "acl")]
+    let preserve_acls = opts.preserve_acls;
+    #[cfg(not(feature = "acl"))]
+    let preserve_acls = false;
+    #[cfg(target_os = "linux")]
+    let preserve_security = opts.preserve_security_xattrs;
+    #[cfg(not(target_os = "linux"))]
+    let preserve_security = false;
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    let preserve_xattrs = opts.preserve_xattrs;
+    #[cfg(not(all(feature = "xattr", any(target_os = "linux", target_os = "macos"))))]
+    let preserve_xattrs = false;
+    if !opts.preserve_permissions
+        && !opts.preserve_timestamps
+        && !preserve_owner
+        && !preserve_acls
+        && !preserve_security
+        && !preserve_xattrs
+    {
+        return Ok(());
+    }
+    let md = fs.metadata(src)?;
+
+    #[cfg(unix)]
+    if opts.preserve_owner {
+        use std::os::unix::fs::MetadataExt;
+
+        fs_chown(dest, Some(md.uid()), Some(md.gid()))?;
+    }
+    if opts.preserve_timestamps {
+        let times = FileTimes::new()
+            .set_accessed(md.accessed()?)
+            .set_modified(md.modified()?);
+        fs.set_times(dest, times)?;
+    }
+    // Set last, as the permissions may make the copy read-only.
+    if opts.preserve_permissions {
+        fs.set_permissions(dest, md.permissions())?;
+    }
+    #[cfg(feature = "acl")]
+    if opts.preserve_acls {
+        dest.set_acl(&src.acl()?)?;
+    }
+    #[cfg(target_os = "linux")]
+    if opts.preserve_security_xattrs {
+        crate::xattr::copy_security(src, dest)?;
+    }
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    if opts.preserve_xattrs {
+        crate::xattr::copy_user(src, dest)?;
+    }
+    Ok(())
+}
+
+/// The total length of the file or of all files in the directory.
+fn tree_len(fs: &dyn Vfs, path: &Utf8Path) -> io::Result<u64> {
+    let mut total = 0;
+    let mut paths = vec![path.to_path_buf()];
+    while let Some(path) = paths.pop() {
+        // Dangling links have no length.
+        let Ok(md) = fs.metadata(&path) else {
+            continue;
+        };
+        if md.is_dir() {
+            paths.extend(fs.read_dir(&path)?);
+        } else {
+            total += md.len();
+        }
+    }
+    Ok(total)
+}
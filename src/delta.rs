@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use camino::Utf8Path;
+
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// What [`Utf8PathExt::delta_sync`](crate::Utf8PathExt::delta_sync) did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeltaStats {
+    /// The bytes that were already up to date and not written.
+    pub unchanged: u64,
+    /// The bytes that were found elsewhere in the destination and moved into place.
+    pub moved: u64,
+    /// The bytes copied from the source.
+    pub written: u64,
+}
+
+/// A range of the new destination, taken from the destination at `source` or
+/// otherwise from the source at the same offset.
+struct Op {
+    target: u64,
+    len: u64,
+    source: Option<u64>,
+}
+
+pub(crate) fn delta_sync(src: &Utf8Path, dest: &Utf8Path) -> io::Result<DeltaStats> {
+    let err = |path: &Utf8Path, action: &str, e: io::Error| {
+        io::Error::new(e.kind(), format!("Could not {action} {path} due to: {e}"))
+    };
+    let mut src_file = File::open(src).map_err(|e| err(src, "open", e))?;
+    let mut dest_file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dest)
+        .map_err(|e| err(dest, "open", e))?;
+
+    let signatures = Signatures::read(&mut dest_file).map_err(|e| err(dest, "read", e))?;
+    let (ops, src_len) = match_blocks(&mut src_file, &mut dest_file, &signatures)
+        .map_err(|e| err(src, "compare", e))?;
+    let stats = apply(&mut src_file, &mut dest_file, ops).map_err(|e| err(dest, "write", e))?;
+    dest_file
+        .set_len(src_len)
+        .and_then(|_| dest_file.sync_all())
+        .map_err(|e| err(dest, "write", e))?;
+    Ok(stats)
+}
+
+/// The weak checksums of the blocks of the destination.
+struct Signatures {
+    len: u64,
+    by_block: Vec<u32>,
+    blocks: HashMap<u32, Vec<u64>>,
+}
+
+impl Signatures {
+    fn read(dest: &mut File) -> io::Result<Self> {
+        let len = dest.metadata()?.len();
+        let mut by_block = Vec::new();
+        let mut blocks: HashMap<u32, Vec<u64>> = HashMap::new();
+        let mut block = vec![0; BLOCK_SIZE];
+        dest.seek(SeekFrom::Start(0))?;
+        while read_block(dest, &mut block)? == BLOCK_SIZE {
+            let weak = Checksum::new(&block).digest();
+            blocks.entry(weak).or_default().push(by_block.len() as u64);
+            by_block.push(weak);
+        }
+        Ok(Self {
+            len,
+            by_block,
+            blocks,
+        })
+    }
+
+    /// The offset of the last block when it is shorter than a full block.
+    fn tail(&self) -> Option<(u64, u64)> {
+        let offset = self.by_block.len() as u64 * BLOCK_SIZE as u64;
+        Some((offset, self.len - offset)).filter(|(_, len)| *len > 0)
+    }
+}
+
+/// The rolling checksum of rsync, which can be moved one byte along in constant time.
+#[derive(Clone, Copy)]
+struct Checksum {
+    a: u32,
+    b: u32,
+}
+
+impl Checksum {
+    fn new(block: &[u8]) -> Self {
+        let (mut a, mut b) = (0u32, 0u32);
+        for (i, &x) in block.iter().enumerate() {
+            a = a.wrapping_add(x as u32);
+            b = b.wrapping_add(((block.len() - i) as u32).wrapping_mul(x as u32));
+        }
+        Self { a, b }
+    }
+
+    fn roll(self, out: u8, into: u8) -> Self {
+        let a = self.a.wrapping_sub(out as u32).wrapping_add(into as u32);
+        let b = self
+            .b
+            .wrapping_sub((BLOCK_SIZE as u32).wrapping_mul(out as u32))
+            .wrapping_add(a);
+        Self { a, b }
+    }
+
+    fn digest(self) -> u32 {
+        (self.a & 0xffff) | (self.b << 16)
+    }
+}
+
+/// Slide a block-sized window over the source and find the blocks that are already
+/// in the destination, preferring the one at the same offset.
+fn match_blocks(src: &mut File, dest: &mut File, sigs: &Signatures) -> io::Result<(Vec<Op>, u64)> {
+    let mut ops = Vec::new();
+    let mut buf = Vec::new();
+    let mut pos = 0;
+    let mut offset = 0;
+    let mut literal_start = 0;
+    let mut checksum = None;
+    let mut dest_block = vec![0; BLOCK_SIZE];
+
+    loop {
+        fill(src, &mut buf, &mut pos)?;
+        let avail = buf.len() - pos;
+        if avail < BLOCK_SIZE {
+            break;
+        }
+        let window = &buf[pos..pos + BLOCK_SIZE];
+        let sum = checksum.unwrap_or_else(|| Checksum::new(window));
+        let weak = sum.digest();
+
+        let same_offset = (offset % BLOCK_SIZE as u64 == 0)
+            .then(|| offset / BLOCK_SIZE as u64)
+            .filter(|&block| sigs.by_block.get(block as usize) == Some(&weak));
+        let candidates = sigs.blocks.get(&weak).into_iter().flatten().copied();
+        let mut found = None;
+        for block in same_offset.into_iter().chain(candidates) {
+            let source = block * BLOCK_SIZE as u64;
+            if read_at(dest, source, &mut dest_block)? == BLOCK_SIZE && dest_block == window {
+                found = Some(source);
+                break;
+            }
+        }
+
+        if let Some(source) = found {
+            push_match(&mut ops, literal_start, offset, BLOCK_SIZE as u64, source);
+            pos += BLOCK_SIZE;
+            offset += BLOCK_SIZE as u64;
+            literal_start = offset;
+            checksum = None;
+        } else if avail > BLOCK_SIZE {
+            checksum = Some(sum.roll(buf[pos], buf[pos + BLOCK_SIZE]));
+            pos += 1;
+            offset += 1;
+        } else {
+            break;
+        }
+    }
+
+    let rest = &buf[pos..];
+    let src_len = offset + rest.len() as u64;
+    match sigs.tail() {
+        Some((source, len)) if len == rest.len() as u64 => {
+            let tail = &mut dest_block[..len as usize];
+            if read_at(dest, source, tail)? == tail.len() && tail == rest {
+                push_match(&mut ops, literal_start, offset, len, source);
+                literal_start = src_len;
+            }
+        }
+        _ => {}
+    }
+    if src_len > literal_start {
+        ops.push(Op {
+            target: literal_start,
+            len: src_len - literal_start,
+            source: None,
+        });
+    }
+    Ok((ops, src_len))
+}
+
+/// Add the block found in the destination, after the bytes from the source before it.
+fn push_match(ops: &mut Vec<Op>, literal_start: u64, target: u64, len: u64, source: u64) {
+    if target > literal_start {
+        ops.push(Op {
+            target: literal_start,
+            len: target - literal_start,
+            source: None,
+        });
+    }
+    ops.push(Op {
+        target,
+        len,
+        source: Some(source),
+    });
+}
+
+/// Write the ranges in an order that never overwrites a block before it is moved.
+///
+/// Blocks moving to a later offset are moved from the end, and blocks moving to an
+/// earlier offset from the start, which is safe within each group. Blocks moving to
+/// an earlier offset that would read what the first group wrote are copied from the
+/// source instead, and everything from the source is written last.
+fn apply(src: &mut File, dest: &mut File, ops: Vec<Op>) -> io::Result<DeltaStats> {
+    let mut stats = DeltaStats::default();
+    let (mut later, mut earlier, mut literals) = (Vec::new(), Vec::new(), Vec::new());
+    for op in ops {
+        match op.source {
+            Some(source) if source == op.target => stats.unchanged += op.len,
+            Some(source) if source < op.target => later.push(op),
+            Some(_) => earlier.push(op),
+            None => literals.push(op),
+        }
+    }
+    let overwritten = |source: u64, len: u64| {
+        // The targets are sorted and don't overlap.
+        let i = later.partition_point(|op: &Op| op.target + op.len <= source);
+        later.get(i).is_some_and(|op| op.target < source + len)
+    };
+    let (earlier, demoted): (Vec<_>, Vec<_>) = earlier
+        .into_iter()
+        .partition(|op| !overwritten(op.source.unwrap_or_default(), op.len));
+    literals.extend(demoted);
+
+    let mut block = vec![0; BLOCK_SIZE];
+    for op in later.iter().rev().chain(&earlier) {
+        let buf = &mut block[..op.len as usize];
+        read_exact_at(dest, op.source.unwrap_or_default(), buf)?;
+        dest.seek(SeekFrom::Start(op.target))?;
+        dest.write_all(buf)?;
+        stats.moved += op.len;
+    }
+    for op in &literals {
+        let mut done = 0;
+        while done < op.len {
+            let len = (op.len - done).min(BLOCK_SIZE as u64) as usize;
+            read_exact_at(src, op.target + done, &mut block[..len])?;
+            dest.seek(SeekFrom::Start(op.target + done))?;
+            dest.write_all(&block[..len])?;
+            done += len as u64;
+        }
+        stats.written += op.len;
+    }
+    Ok(stats)
+}
+
+/// Make sure that the buffer holds more than a block after `pos`, unless the source
+/// ends before, dropping the bytes before `pos`.
+fn fill(src: &mut File, buf: &mut Vec<u8>, pos: &mut usize) -> io::Result<()> {
+    if buf.len() - *pos > BLOCK_SIZE {
+        return Ok(());
+    }
+    buf.drain(..*pos);
+    *pos = 0;
+    let old_len = buf.len();
+    buf.resize(old_len + 4 * BLOCK_SIZE, 0);
+    let n = read_block(src, &mut buf[old_len..])?;
+    buf.truncate(old_len + n);
+    Ok(())
+}
+
+fn read_at(file: &mut File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+    file.seek(SeekFrom::Start(offset))?;
+    read_block(file, buf)
+}
+
+fn read_exact_at(file: &mut File, offset: u64, buf: &mut [u8]) -> io::Result<()> {
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+/// Read until the buffer is full or the end of the file, returning the bytes read.
+fn read_block(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
@@ -0,0 +1,156 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufReader, Read},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+#[cfg(feature = "hash")]
+use crate::digest::{file_digest, Digest};
+use crate::Utf8PathExt;
+
+/// The differences between two directory trees, see [`Utf8PathExt::diff_dir`].
+///
+/// All paths are relative to the compared directories and sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffReport {
+    /// Files that only exist in the new tree.
+    pub added: Vec<DiffEntry>,
+    /// Files that only exist in the old tree.
+    pub removed: Vec<DiffEntry>,
+    /// Files that exist in both trees with different content.
+    pub changed: Vec<ChangedEntry>,
+}
+
+impl DiffReport {
+    /// Returns true if the trees have the same files with the same content.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A file that was added or removed.
+///
+/// Non-exhaustive, as the `hash` feature adds the digest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct DiffEntry {
+    pub path: Utf8PathBuf,
+    pub size: u64,
+    #[cfg(feature = "hash")]
+    pub digest: Option<Digest>,
+}
+
+/// A file with different content in the old and new tree.
+///
+/// Non-exhaustive, as the `hash` feature adds the digests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct ChangedEntry {
+    pub path: Utf8PathBuf,
+    pub old_size: u64,
+    pub new_size: u64,
+    #[cfg(feature = "hash")]
+    pub old_digest: Option<Digest>,
+    #[cfg(feature = "hash")]
+    pub new_digest: Option<Digest>,
+}
+
+pub(crate) fn diff_dirs(old: &Utf8Path, new: &Utf8Path, hashed: bool) -> io::Result<DiffReport> {
+    old.assert_dir()?;
+    new.assert_dir()?;
+    let old_files = relative_files(old)?;
+    let new_files = relative_files(new)?;
+    let mut report = DiffReport::default();
+
+    for rel in old_files.difference(&new_files) {
+        report.removed.push(entry(old, rel, hashed)?);
+    }
+    for rel in new_files.difference(&old_files) {
+        report.added.push(entry(new, rel, hashed)?);
+    }
+    for rel in old_files.intersection(&new_files) {
+        let (old_path, new_path) = (old.join(rel), new.join(rel));
+        let old_size = old_path.metadata()?.len();
+        let new_size = new_path.metadata()?.len();
+
+        #[cfg(feature = "hash")]
+        if hashed {
+            let old_digest = file_digest(&old_path)?;
+            let new_digest = file_digest(&new_path)?;
+            if old_digest != new_digest {
+                report.changed.push(ChangedEntry {
+                    path: rel.clone(),
+                    old_size,
+                    new_size,
+                    old_digest: Some(old_digest),
+                    new_digest: Some(new_digest),
+                });
+            }
+            continue;
+        }
+
+        if old_size != new_size || !same_content(&old_path, &new_path)? {
+            report.changed.push(ChangedEntry {
+                path: rel.clone(),
+                old_size,
+                new_size,
+                #[cfg(feature = "hash")]
+                old_digest: None,
+                #[cfg(feature = "hash")]
+                new_digest: None,
+            });
+        }
+    }
+    Ok(report)
+}
+
+fn relative_files(dir: &Utf8Path) -> io::Result<BTreeSet<Utf8PathBuf>> {
+    dir.ls()
+        .recurse()
+        .files()
+        .relative_paths()
+        .try_iter()
+        .collect()
+}
+
+#[allow(unused_variables)]
+fn entry(dir: &Utf8Path, rel: &Utf8Path, hashed: bool) -> io::Result<DiffEntry> {
+    let path = dir.join(rel);
+    Ok(DiffEntry {
+        path: rel.to_path_buf(),
+        size: path.metadata()?.len(),
+        #[cfg(feature = "hash")]
+        digest: hashed.then(|| file_digest(&path)).transpose()?,
+    })
+}
+
+/// Compare two files of the same length byte by byte.
+pub(crate) fn same_content(a: &Utf8Path, b: &Utf8Path) -> io::Result<bool> {
+    let open = |path: &Utf8Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| io::Error::new(e.kind(), format!("Could not open {path} due to: {e}")))
+    };
+    let (mut a, mut b) = (open(a)?, open(b)?);
+    let mut buf_a = [0; 8192];
+    let mut buf_b = [0; 8192];
+    loop {
+        let n = a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(b.read(&mut buf_b)? == 0);
+        }
+        match b.read_exact(&mut buf_b[..n]) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
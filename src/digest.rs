@@ -0,0 +1,141 @@
+use std::{fmt, fs::File, io, str::FromStr};
+
+use camino::Utf8Path;
+use sha2::{Digest as _, Sha256, Sha512_256};
+
+/// A hash algorithm with a 32 byte output, see [`Utf8PathExt::merkle_tree`](crate::Utf8PathExt::merkle_tree).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// SHA-256, used everywhere else in this crate.
+    #[default]
+    Sha256,
+    /// SHA-512/256, which is faster than SHA-256 on 64-bit CPUs without SHA extensions.
+    Sha512_256,
+}
+
+/// A hasher for one of the [`HashAlgorithm`]s.
+pub(crate) enum Hasher {
+    Sha256(Sha256),
+    Sha512_256(Sha512_256),
+}
+
+impl Hasher {
+    pub(crate) fn new(algo: HashAlgorithm) -> Self {
+        match algo {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512_256 => Self::Sha512_256(Sha512_256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Sha256(h) => h.update(bytes),
+            Self::Sha512_256(h) => h.update(bytes),
+        }
+    }
+
+    pub(crate) fn finalize(self) -> Digest {
+        match self {
+            Self::Sha256(h) => Digest(h.finalize().into()),
+            Self::Sha512_256(h) => Digest(h.finalize().into()),
+        }
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A SHA-256 digest of file content, displayed as lowercase hex.
+///
+/// Digests of a [`HashAlgorithm`] other than SHA-256 are stored in it as well.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Digest([u8; 32]);
+
+impl Digest {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The digest of a byte slice.
+    pub fn of(bytes: &[u8]) -> Self {
+        Self(Sha256::digest(bytes).into())
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({self})")
+    }
+}
+
+impl FromStr for Digest {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("\"{s}\" is not a valid hex encoded SHA-256 digest"),
+            )
+        };
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let mut bytes = [0; 32];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Digest {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Digest {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Stream the file through SHA-256.
+pub(crate) fn file_digest(path: &Utf8Path) -> io::Result<Digest> {
+    file_digest_with(path, HashAlgorithm::Sha256)
+}
+
+/// Stream the file through the hash algorithm.
+pub(crate) fn file_digest_with(path: &Utf8Path, algo: HashAlgorithm) -> io::Result<Digest> {
+    let mut file = File::open(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not open {path} due to: {e}")))?;
+    let mut hasher = Hasher::new(algo);
+    io::copy(&mut file, &mut hasher)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))?;
+    Ok(hasher.finalize())
+}
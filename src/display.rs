@@ -0,0 +1,81 @@
+use std::path::MAIN_SEPARATOR;
+
+use camino::Utf8Path;
+
+use crate::expand::home_dir;
+
+const ELLIPSIS: char = '…';
+
+/// Render the path with the home directory replaced by `~`, and if `max_width`
+/// is given, with the middle components replaced by `…` so that it fits.
+pub(crate) fn compact(path: &Utf8Path, max_width: Option<usize>) -> String {
+    let mut comps: Vec<String> = Vec::new();
+    let home_rel = home_dir().and_then(|home| path.strip_prefix(home).ok().map(|p| p.to_owned()));
+    let shown = match &home_rel {
+        Some(rel) => {
+            comps.push("~".to_string());
+            rel.as_path()
+        }
+        None => path,
+    };
+    for comp in shown.components() {
+        comps.push(comp.as_str().to_string());
+    }
+
+    let full = join(&comps);
+    let Some(max_width) = max_width else {
+        return full;
+    };
+    if width(&full) <= max_width || comps.len() < 2 {
+        return truncate_start(&full, max_width);
+    }
+
+    // Keep the first component and as many trailing components as fit.
+    let head = &comps[0];
+    let mut tail: Vec<String> = Vec::new();
+    for comp in comps[1..].iter().rev() {
+        let mut candidate = vec![head.clone(), ELLIPSIS.to_string()];
+        candidate.push(comp.clone());
+        candidate.extend(tail.iter().rev().cloned());
+        if width(&join(&candidate)) > max_width {
+            break;
+        }
+        tail.push(comp.clone());
+    }
+    if tail.is_empty() {
+        let name = comps.last().unwrap();
+        return truncate_start(name, max_width);
+    }
+    let mut out = vec![head.clone(), ELLIPSIS.to_string()];
+    out.extend(tail.into_iter().rev());
+    join(&out)
+}
+
+fn join(comps: &[String]) -> String {
+    let mut s = String::new();
+    for comp in comps {
+        if !s.is_empty() && !s.ends_with(['/', MAIN_SEPARATOR]) {
+            s.push(MAIN_SEPARATOR);
+        }
+        s.push_str(comp);
+    }
+    s
+}
+
+fn width(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Cut off the start of `s` so that it fits in `max_width`, marking it with `…`.
+fn truncate_start(s: &str, max_width: usize) -> String {
+    let len = width(s);
+    if len <= max_width {
+        return s.to_string();
+    }
+    if max_width == 0 {
+        return String::new();
+    }
+    let mut out = String::from(ELLIPSIS);
+    out.extend(s.chars().skip(len - (max_width - 1)));
+    out
+}
@@ -0,0 +1,69 @@
+use std::io;
+
+use camino::Utf8Path;
+
+use crate::{OverwritePolicy, Utf8PathExt};
+
+/// Write a directory embedded with [`include_dir!`](https://docs.rs/include_dir) to
+/// `target`, creating directories as needed.
+///
+/// Existing files are handled according to `policy` and files in `target` that
+/// aren't in the embedded directory are left as is.
+#[cfg(feature = "include_dir")]
+pub fn materialize<P: AsRef<Utf8Path>>(
+    dir: &include_dir::Dir,
+    target: P,
+    policy: OverwritePolicy,
+) -> io::Result<()> {
+    let target = target.as_ref();
+    target.mkdirs()?;
+    let mut dirs = vec![dir];
+    while let Some(sub_dir) = dirs.pop() {
+        for file in sub_dir.files() {
+            // Embedded paths are relative to the root of the include, not to `dir`.
+            let rel = file.path().strip_prefix(dir.path()).unwrap_or(file.path());
+            let rel = Utf8Path::from_path(rel).ok_or_else(|| {
+                io::Error::other(format!("Embedded path {rel:?} is not valid UTF-8"))
+            })?;
+            write_file(&target.join(rel), file.contents(), policy)?;
+        }
+        dirs.extend(sub_dir.dirs());
+    }
+    Ok(())
+}
+
+/// Write the files of a [`RustEmbed`](https://docs.rs/rust-embed) asset folder to
+/// `target`, creating directories as needed.
+///
+/// Existing files are handled according to `policy` and files in `target` that
+/// aren't in the embedded folder are left as is.
+#[cfg(feature = "rust-embed")]
+pub fn materialize_rust_embed<E: rust_embed::RustEmbed, P: AsRef<Utf8Path>>(
+    target: P,
+    policy: OverwritePolicy,
+) -> io::Result<()> {
+    let target = target.as_ref();
+    target.mkdirs()?;
+    for name in E::iter() {
+        let file = E::get(&name)
+            .ok_or_else(|| io::Error::other(format!("Embedded file {name} is missing")))?;
+        write_file(&target.join(name.as_ref()), &file.data, policy)?;
+    }
+    Ok(())
+}
+
+fn write_file(path: &Utf8Path, contents: &[u8], policy: OverwritePolicy) -> io::Result<()> {
+    if path.exists() {
+        match policy {
+            OverwritePolicy::Overwrite | OverwritePolicy::IfNewer => {}
+            OverwritePolicy::Skip => return Ok(()),
+            OverwritePolicy::Error => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("Could not write {path} as it already exists"),
+                ))
+            }
+        }
+    }
+    path.write(contents)
+}
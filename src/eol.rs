@@ -0,0 +1,47 @@
+/// The characters that end a line in a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, used on Unix-like systems.
+    Lf,
+    /// `\r\n`, used on Windows.
+    CrLf,
+}
+
+impl LineEnding {
+    /// The line ending of the platform the program runs on.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Self::CrLf
+        } else {
+            Self::Lf
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        Self::native()
+    }
+}
+
+/// Replace all `\n` and `\r\n` line endings with `ending`.
+pub(crate) fn with_line_endings(s: &str, ending: LineEnding) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut lines = s.split('\n').peekable();
+    while let Some(line) = lines.next() {
+        if lines.peek().is_none() {
+            out.push_str(line);
+        } else {
+            out.push_str(line.strip_suffix('\r').unwrap_or(line));
+            out.push_str(ending.as_str());
+        }
+    }
+    out
+}
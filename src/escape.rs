@@ -0,0 +1,139 @@
+use std::io;
+
+use crate::Platform;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Percent-encode every byte of `s` for which `keep` returns false.
+pub(crate) fn percent_encode<F: Fn(u8) -> bool>(s: &str, keep: F) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if keep(b) {
+            out.push(b as char);
+        } else {
+            out.push('%');
+            out.push(HEX[(b >> 4) as usize] as char);
+            out.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Decode all `%XX` sequences in `s`, failing on malformed sequences or if the
+/// decoded bytes are not valid UTF-8.
+pub(crate) fn percent_decode(s: &str) -> io::Result<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = bytes
+                .get(i + 1..i + 3)
+                // `from_str_radix` also accepts a sign, as in `%+1`.
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Invalid percent-encoding at position {i} in \"{s}\""),
+                    )
+                })?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Percent-decoded \"{s}\" is not valid UTF-8"),
+        )
+    })
+}
+
+/// Unreserved characters as defined by RFC 3986.
+pub(crate) fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+/// A shell to quote paths for, see [`crate::Utf8PathExt::shell_quoted_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    /// POSIX `sh` and compatible shells like bash and zsh.
+    Sh,
+    /// Windows `cmd.exe`.
+    Cmd,
+    /// Windows PowerShell and PowerShell Core.
+    PowerShell,
+}
+
+impl Shell {
+    /// The default shell of the current platform: [`Shell::PowerShell`] on Windows
+    /// and [`Shell::Sh`] everywhere else.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Shell::PowerShell
+        } else {
+            Shell::Sh
+        }
+    }
+
+    pub(crate) fn quote(self, s: &str) -> String {
+        let is_safe = |c: char| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '_' | '-' | '.' | '/' | ':')
+                || match self {
+                    Shell::Sh => matches!(c, ',' | '+' | '=' | '@' | '%'),
+                    Shell::Cmd | Shell::PowerShell => c == '\\',
+                }
+        };
+        if !s.is_empty() && s.chars().all(is_safe) {
+            return s.to_string();
+        }
+        match self {
+            Shell::Sh => format!("'{}'", s.replace('\'', r"'\''")),
+            Shell::PowerShell => format!("'{}'", s.replace('\'', "''")),
+            Shell::Cmd => format!("\"{}\"", s.replace('"', "\"\"")),
+        }
+    }
+}
+
+/// Percent-encode what `platform` doesn't allow in a file name, and `%` itself so the
+/// result can be decoded again. `.` and `..` are left as is.
+pub(crate) fn escape_name_for(name: &str, platform: Platform) -> String {
+    if name == "." || name == ".." {
+        return name.to_string();
+    }
+    let mut escaped = percent_encode(name, |b| match platform {
+        Platform::Windows => b >= 0x20 && !b"<>:\"|?*\\%".contains(&b),
+        Platform::MacOs => !matches!(b, b':' | b'%'),
+        Platform::Linux => b != b'%',
+    });
+    if platform == Platform::Windows {
+        // Windows strips trailing dots and spaces.
+        if let Some(last @ (b'.' | b' ')) = escaped.bytes().last() {
+            escaped.pop();
+            escaped.push_str(if last == b'.' { "%2E" } else { "%20" });
+        }
+        if is_reserved_on_windows(&escaped) {
+            escaped = percent_encode(&escaped[..1], |_| false) + &escaped[1..];
+        }
+    }
+    escaped
+}
+
+/// Device names like `CON` or `com1.txt` that can't be used as file names on Windows,
+/// whatever the extension.
+pub(crate) fn is_reserved_on_windows(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_uppercase();
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && matches!(stem.as_bytes()[3..], [b'1'..=b'9'])
+        }
+    }
+}
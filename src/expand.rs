@@ -0,0 +1,70 @@
+use std::{env, io, path::MAIN_SEPARATOR};
+
+use camino::Utf8PathBuf;
+
+/// The home directory of the current user, taken from `HOME` (or `USERPROFILE` on Windows).
+pub(crate) fn home_dir() -> Option<Utf8PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var(var)
+        .ok()
+        .filter(|home| !home.is_empty())
+        .map(Utf8PathBuf::from)
+}
+
+/// Replace a leading `~` with the home directory of the current user.
+///
+/// `~user` style paths are returned unchanged.
+pub(crate) fn expand_home(s: &str) -> io::Result<String> {
+    let rest = match s.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', MAIN_SEPARATOR]) => rest,
+        _ => return Ok(s.to_string()),
+    };
+    let home = home_dir().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not expand \"{s}\" as the home directory is unknown"),
+        )
+    })?;
+    Ok(format!("{home}{rest}"))
+}
+
+/// Replace `$VAR` and `${VAR}` with the value of the environment variable.
+///
+/// A `$` that is not followed by a variable name is kept as is.
+pub(crate) fn expand_env(s: &str) -> io::Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (name, remaining) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced.find('}').ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Unclosed '${{' in \"{s}\""),
+                )
+            })?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let value = env::var(name).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not expand ${name} in \"{s}\" due to: {e}"),
+            )
+        })?;
+        out.push_str(&value);
+        rest = remaining;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
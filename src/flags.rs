@@ -0,0 +1,130 @@
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// Linux inode attributes, as changed with `chattr`.
+#[derive(Clone, Copy)]
+pub(crate) enum Flag {
+    Immutable,
+    AppendOnly,
+}
+
+#[cfg(target_os = "linux")]
+impl Flag {
+    /// The bit from linux/fs.h.
+    fn bit(self) -> libc::c_int {
+        match self {
+            Flag::Immutable => 0x10,  // FS_IMMUTABLE_FL
+            Flag::AppendOnly => 0x20, // FS_APPEND_FL
+        }
+    }
+}
+
+/// Whether the path has the flag. Always false on other platforms than Linux.
+#[cfg(target_os = "linux")]
+pub(crate) fn has(path: &Utf8Path, flag: Flag) -> bool {
+    get_flags(path).is_ok_and(|flags| flags & flag.bit() != 0)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn has(_path: &Utf8Path, _flag: Flag) -> bool {
+    false
+}
+
+/// Set or clear the flag, which usually requires root privileges.
+#[cfg(target_os = "linux")]
+pub(crate) fn set(path: &Utf8Path, flag: Flag, on: bool) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not change attributes of {path} due to: {e}"),
+        )
+    };
+    let flags = get_flags(path).map_err(err)?;
+    let flags = if on {
+        flags | flag.bit()
+    } else {
+        flags & !flag.bit()
+    };
+    let file = open(path).map_err(err)?;
+    // SAFETY: the file descriptor is valid and the kernel reads an int from `flags`.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_SETFLAGS, &flags) };
+    if ret != 0 {
+        return Err(err(io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn set(path: &Utf8Path, _flag: Flag, _on: bool) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Could not change attributes of {path} as they are only supported on Linux"),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn open(path: &Utf8Path) -> io::Result<std::fs::File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    // Opening read only works for directories and for files that are immutable.
+    std::fs::File::options()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(path)
+}
+
+#[cfg(target_os = "linux")]
+fn get_flags(path: &Utf8Path) -> io::Result<libc::c_int> {
+    use std::os::fd::AsRawFd;
+
+    let file = open(path)?;
+    let mut flags: libc::c_int = 0;
+    // SAFETY: the file descriptor is valid and the kernel writes an int to `flags`.
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), libc::FS_IOC_GETFLAGS, &mut flags) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(flags)
+}
+
+/// Add a hint to an error from removing `path` if the path, its parent or something
+/// in it is immutable or append-only, as that makes the removal fail.
+pub(crate) fn explain_rm_error(path: &Utf8Path, e: io::Error) -> io::Error {
+    match flagged_path(path) {
+        Some(flagged) => io::Error::new(
+            e.kind(),
+            format!(
+                "{e}. {flagged} is immutable or append-only, which has to be cleared \
+                 first, e.g. with `chattr -i -a {flagged}`"
+            ),
+        ),
+        None => e,
+    }
+}
+
+fn flagged_path(path: &Utf8Path) -> Option<Utf8PathBuf> {
+    use crate::Utf8PathExt;
+
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let is_flagged = |p: &Utf8Path| has(p, Flag::Immutable) || has(p, Flag::AppendOnly);
+    let parent = path.parent().filter(|p| !p.as_str().is_empty());
+    if let Some(parent) = parent.filter(|p| is_flagged(p)) {
+        return Some(parent.to_path_buf());
+    }
+    if is_flagged(path) {
+        return Some(path.to_path_buf());
+    }
+    if path.symlink_metadata().is_ok_and(|md| md.is_dir()) {
+        let base = path.to_path_buf();
+        return path
+            .ls()
+            .recurse_if(move |rel| !base.join(rel).is_symlink())
+            .find(|p| !p.is_symlink() && is_flagged(p));
+    }
+    None
+}
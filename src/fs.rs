@@ -1,24 +1,52 @@
-use std::io;
+use std::{io, time::SystemTime};
 
 use camino::Utf8Path;
 
+use crate::{
+    io_stats::{count, Counter},
+    IoStats,
+};
+
 /// Copies the contents of one file to another. This function will also copy the
 /// permission bits of the original file to the destination file.
 ///
 /// Wrapper for [`fs::copy`](https://doc.rust-lang.org/stable/std/fs/fn.copy.html).
 pub fn fs_copy(from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
-    std::fs::copy(from, to)
-        .map_err(|e| io::Error::other(format!("Could not copy {from} to {to} due to: {e}")))
-        .map(|_| ())
+    let len = std::fs::copy(from, to).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not copy {from} to {to} due to: {e}"),
+        )
+    })?;
+    count(Counter::Copies, 1);
+    count(Counter::BytesCopied, len);
+    Ok(())
+}
+
+/// Creates a new hard link `link` to the file `original`.
+///
+/// Wrapper for [`fs::hard_link`](https://doc.rust-lang.org/stable/std/fs/fn.hard_link.html).
+pub fn fs_hard_link(original: &Utf8Path, link: &Utf8Path) -> io::Result<()> {
+    std::fs::hard_link(original, link).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not link {link} to {original} due to: {e}"),
+        )
+    })
 }
 
 /// Rename a file or directory to a new name, replacing the original file if to already exists.
 ///
 /// Wrapper for [`fs::rename`](https://doc.rust-lang.org/stable/std/fs/fn.rename.html).
 pub fn fs_rename(from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
-    std::fs::rename(from, to)
-        .map_err(|e| io::Error::other(format!("Could not rename {from} to {to} due to: {e}")))
-        .map(|_| ())
+    std::fs::rename(from, to).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not rename {from} to {to} due to: {e}"),
+        )
+    })?;
+    count(Counter::Renames, 1);
+    Ok(())
 }
 
 /// Removes a directory at this path, after removing all its contents. Use carefully!
@@ -26,8 +54,9 @@ pub fn fs_rename(from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
 /// Wrapper for [`fs::remove_dir_all`](https://doc.rust-lang.org/stable/std/fs/fn.remove_dir_all.html).
 pub fn fs_remove_dir_all(path: &Utf8Path) -> io::Result<()> {
     std::fs::remove_dir_all(path)
-        .map_err(|e| io::Error::other(format!("Could not remove {path} due to: {e}")))
-        .map(|_| ())
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not remove {path} due to: {e}")))?;
+    count(Counter::DirsRemoved, 1);
+    Ok(())
 }
 
 /// Removes a file from the filesystem.
@@ -35,42 +64,67 @@ pub fn fs_remove_dir_all(path: &Utf8Path) -> io::Result<()> {
 /// Wrapper for [`fs::remove_file`](https://doc.rust-lang.org/stable/std/fs/fn.remove_file.html).
 pub fn fs_remove_file(path: &Utf8Path) -> io::Result<()> {
     std::fs::remove_file(path)
-        .map_err(|e| io::Error::other(format!("Could not remove {path} due to: {e}")))
-        .map(|_| ())
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not remove {path} due to: {e}")))?;
+    count(Counter::FilesRemoved, 1);
+    Ok(())
 }
 
 /// Creates a new, empty directory at the provided path.
 ///
 /// Wrapper for [`fs::create_dir`](https://doc.rust-lang.org/stable/std/fs/fn.create_dir.html).
 pub fn fs_create_dir(path: &Utf8Path) -> io::Result<()> {
-    std::fs::create_dir(path)
-        .map_err(|e| io::Error::other(format!("Could not create directory {path} due to: {e}")))
+    std::fs::create_dir(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not create directory {path} due to: {e}"),
+        )
+    })?;
+    count(Counter::DirsCreated, 1);
+    Ok(())
 }
 
 /// Recursively create a directory and all of its parent components if they are missing.
 ///
 /// Wrapper for [`fs::create_dir_all`](https://doc.rust-lang.org/stable/std/fs/fn.create_dir_all.html).
 pub fn fs_create_dir_all(path: &Utf8Path) -> io::Result<()> {
+    // Only the directories that are missing are created.
+    let missing = if IoStats::is_enabled() {
+        path.ancestors()
+            .take_while(|dir| !dir.as_str().is_empty() && !dir.is_dir())
+            .count()
+    } else {
+        0
+    };
     std::fs::create_dir_all(path).map_err(|e| {
-        io::Error::other(format!(
-            "Could not create directories for {path} due to: {e}"
-        ))
-    })
+        io::Error::new(
+            e.kind(),
+            format!("Could not create directories for {path} due to: {e}"),
+        )
+    })?;
+    count(Counter::DirsCreated, missing as u64);
+    Ok(())
 }
 
 /// Read the entire contents of a file into a bytes vector.
 ///
 /// Wrapper for [`fs::read`](https://doc.rust-lang.org/stable/std/fs/fn.read.html).
 pub fn fs_read(path: &Utf8Path) -> io::Result<Vec<u8>> {
-    std::fs::read(path).map_err(|e| io::Error::other(format!("Could not read {path} due to: {e}")))
+    let bytes = std::fs::read(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))?;
+    count(Counter::Reads, 1);
+    count(Counter::BytesRead, bytes.len() as u64);
+    Ok(bytes)
 }
 
 /// Read the entire contents of a file into a string.
 ///
 /// Wrapper for [`fs::read_to_string`](https://doc.rust-lang.org/stable/std/fs/fn.read_to_string.html).
 pub fn fs_read_to_string(path: &Utf8Path) -> io::Result<String> {
-    std::fs::read_to_string(path)
-        .map_err(|e| io::Error::other(format!("Could not read {path} due to: {e}")))
+    let s = std::fs::read_to_string(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))?;
+    count(Counter::Reads, 1);
+    count(Counter::BytesRead, s.len() as u64);
+    Ok(s)
 }
 
 /// Write a slice as the entire contents of a file.
@@ -78,5 +132,180 @@ pub fn fs_read_to_string(path: &Utf8Path) -> io::Result<String> {
 /// Wrapper for [`fs::write`](https://doc.rust-lang.org/stable/std/fs/fn.write.html).
 pub fn fs_write(path: &Utf8Path, bytes: &[u8]) -> io::Result<()> {
     std::fs::write(path, bytes)
-        .map_err(|e| io::Error::other(format!("Could not write to {path} due to: {e}")))
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not write to {path} due to: {e}")))?;
+    count(Counter::Writes, 1);
+    count(Counter::BytesWritten, bytes.len() as u64);
+    Ok(())
+}
+
+/// Given a path, query the file system to get information about a file, directory, etc.
+///
+/// Wrapper for [`fs::metadata`](https://doc.rust-lang.org/stable/std/fs/fn.metadata.html).
+pub fn fs_metadata(path: &Utf8Path) -> io::Result<std::fs::Metadata> {
+    count(Counter::MetadataCalls, 1);
+    std::fs::metadata(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read metadata of {path} due to: {e}"),
+        )
+    })
+}
+
+/// Changes the timestamps of a file or directory.
+///
+/// Wrapper for [`File::set_times`](https://doc.rust-lang.org/stable/std/fs/struct.File.html#method.set_times).
+pub fn fs_set_times(path: &Utf8Path, times: std::fs::FileTimes) -> io::Result<()> {
+    open_for_set_times(path)
+        .and_then(|file| file.set_times(times))
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not set times of {path} due to: {e}"),
+            )
+        })
+}
+
+#[cfg(windows)]
+fn open_for_set_times(path: &Utf8Path) -> io::Result<std::fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    // Needed to open directories.
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x02000000;
+
+    std::fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+}
+
+#[cfg(not(windows))]
+fn open_for_set_times(path: &Utf8Path) -> io::Result<std::fs::File> {
+    std::fs::File::open(path)
+}
+
+/// Set the modification time by path, without opening the file or following a
+/// symlink, so that it works on unreadable files and dangling symlinks as well.
+pub(crate) fn set_mtime_nofollow(path: &Utf8Path, mtime: SystemTime) -> io::Result<()> {
+    sys_set_mtime_nofollow(path, mtime).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not set times of {path} due to: {e}"),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn sys_set_mtime_nofollow(path: &Utf8Path, mtime: SystemTime) -> io::Result<()> {
+    use std::{ffi::CString, time::UNIX_EPOCH};
+
+    let (secs, nanos) = match mtime.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos() as i64),
+        // The nanoseconds are always counted forward from the seconds.
+        Err(e) => match e.duration() {
+            d if d.subsec_nanos() == 0 => (-(d.as_secs() as i64), 0),
+            d => (
+                -(d.as_secs() as i64) - 1,
+                1_000_000_000 - d.subsec_nanos() as i64,
+            ),
+        },
+    };
+    // SAFETY: all-zero is a valid `timespec`, which has private padding on some targets.
+    let mut times: [libc::timespec; 2] = unsafe { std::mem::zeroed() };
+    times[0].tv_nsec = libc::UTIME_OMIT as _;
+    times[1].tv_sec = secs as _;
+    times[1].tv_nsec = nanos as _;
+
+    let c_path =
+        CString::new(path.as_str()).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    // SAFETY: `c_path` is a valid nul terminated string and `times` holds two timespecs.
+    let ret = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn sys_set_mtime_nofollow(path: &Utf8Path, mtime: SystemTime) -> io::Result<()> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x02000000;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x00200000;
+
+    std::fs::OpenOptions::new()
+        .access_mode(FILE_WRITE_ATTRIBUTES)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(path)?
+        .set_times(std::fs::FileTimes::new().set_modified(mtime))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn sys_set_mtime_nofollow(path: &Utf8Path, mtime: SystemTime) -> io::Result<()> {
+    open_for_set_times(path)?.set_times(std::fs::FileTimes::new().set_modified(mtime))
+}
+
+/// Changes the permissions of a file or directory.
+///
+/// Wrapper for [`fs::set_permissions`](https://doc.rust-lang.org/stable/std/fs/fn.set_permissions.html).
+pub fn fs_set_permissions(path: &Utf8Path, perm: std::fs::Permissions) -> io::Result<()> {
+    std::fs::set_permissions(path, perm).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not set permissions of {path} due to: {e}"),
+        )
+    })
+}
+
+/// Change the owner and group of a file or directory.
+///
+/// Wrapper for [`chown`](https://doc.rust-lang.org/stable/std/os/unix/fs/fn.chown.html).
+#[cfg(unix)]
+pub fn fs_chown(path: &Utf8Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    std::os::unix::fs::chown(path, uid, gid).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not change owner of {path} due to: {e}"),
+        )
+    })
+}
+
+/// Change the owner and group of a symlink itself rather than its target.
+///
+/// Wrapper for [`lchown`](https://doc.rust-lang.org/stable/std/os/unix/fs/fn.lchown.html).
+#[cfg(unix)]
+pub fn fs_lchown(path: &Utf8Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    std::os::unix::fs::lchown(path, uid, gid).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not change owner of {path} due to: {e}"),
+        )
+    })
+}
+
+/// Removes an empty directory.
+///
+/// Wrapper for [`fs::remove_dir`](https://doc.rust-lang.org/stable/std/fs/fn.remove_dir.html).
+pub fn fs_remove_dir(path: &Utf8Path) -> io::Result<()> {
+    std::fs::remove_dir(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not remove {path} due to: {e}")))?;
+    count(Counter::DirsRemoved, 1);
+    Ok(())
+}
+
+/// Returns the canonical, absolute form of a path with all intermediate components
+/// normalized and symbolic links resolved.
+///
+/// Wrapper for [`fs::canonicalize`](https://doc.rust-lang.org/stable/std/fs/fn.canonicalize.html).
+pub fn fs_canonicalize(path: &Utf8Path) -> io::Result<std::path::PathBuf> {
+    std::fs::canonicalize(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not resolve {path} due to: {e}")))
 }
@@ -0,0 +1,217 @@
+use std::{
+    error::Error,
+    fmt,
+    fs::{File, Metadata},
+    io::{self, Read, Write},
+    time::SystemTime,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    fs::{fs_hard_link, fs_remove_file, fs_rename},
+    lock::is_current,
+    snapshot::fnv1a,
+    temp::sibling_temp_path,
+    Utf8PathExt,
+};
+
+/// Identifies a version of a file by its modification time, length and a hash of
+/// its content, see [`Utf8PathExt::write_with_generation`].
+///
+/// The modification time alone is not enough, as its resolution ranges from
+/// nanoseconds to two seconds depending on the file system, and some only update it
+/// once per kernel tick. The content hash tells apart two writes of the same length
+/// within that resolution, unless they wrote the same content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Generation {
+    modified: SystemTime,
+    len: u64,
+    content_hash: u64,
+}
+
+impl Generation {
+    pub fn modified(&self) -> SystemTime {
+        self.modified
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The FNV-1a hash of the content.
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    fn of(md: &Metadata, content: &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            modified: md.modified()?,
+            len: md.len(),
+            content_hash: fnv1a(content),
+        })
+    }
+}
+
+/// The error inside the `io::Error` returned by [`Utf8PathExt::write_with_generation`]
+/// when the file has changed since it was read.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let path = dir.join("config.toml");
+/// let generation = path.write_with_generation("a = 1", None).unwrap();
+/// let err = path.write_with_generation("a = 2", None).unwrap_err();
+/// let conflict = err.get_ref().and_then(|e| e.downcast_ref::<GenerationConflict>());
+/// assert_eq!(conflict.unwrap().actual(), Some(generation));
+/// ```
+#[derive(Debug)]
+pub struct GenerationConflict {
+    path: Utf8PathBuf,
+    expected: Option<Generation>,
+    actual: Option<Generation>,
+}
+
+impl GenerationConflict {
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// The generation the writer expected, `None` if it expected no file.
+    pub fn expected(&self) -> Option<Generation> {
+        self.expected
+    }
+
+    /// The generation found on disk, `None` if there was no file.
+    pub fn actual(&self) -> Option<Generation> {
+        self.actual
+    }
+}
+
+impl fmt::Display for GenerationConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.expected, self.actual) {
+            (None, Some(_)) => write!(f, "Could not write {} as it already exists", self.path),
+            (Some(_), None) => write!(f, "Could not write {} as it was removed", self.path),
+            _ => write!(
+                f,
+                "Could not write {} as it was changed since it was read",
+                self.path
+            ),
+        }
+    }
+}
+
+impl Error for GenerationConflict {}
+
+pub(crate) fn generation(path: &Utf8Path) -> io::Result<Generation> {
+    read_with_generation(path).map(|(_, generation)| generation)
+}
+
+pub(crate) fn read_with_generation(path: &Utf8Path) -> io::Result<(Vec<u8>, Generation)> {
+    let mut file = File::open(path).map_err(|e| err(path, "open", e))?;
+    read_file(path, &mut file)
+}
+
+fn read_file(path: &Utf8Path, file: &mut File) -> io::Result<(Vec<u8>, Generation)> {
+    let md = file
+        .metadata()
+        .map_err(|e| err(path, "read metadata of", e))?;
+    let mut buf = Vec::with_capacity(md.len() as usize);
+    file.read_to_end(&mut buf)
+        .map_err(|e| err(path, "read", e))?;
+    let generation = Generation::of(&md, &buf).map_err(|e| err(path, "read metadata of", e))?;
+    Ok((buf, generation))
+}
+
+pub(crate) fn write_with_generation(
+    path: &Utf8Path,
+    buf: &[u8],
+    expected: Option<Generation>,
+) -> io::Result<Generation> {
+    if let Some(parent) = path.parent() {
+        parent.mkdirs()?;
+    }
+    let conflict = |actual| {
+        io::Error::other(GenerationConflict {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        })
+    };
+
+    let temp = sibling_temp_path(path, "generation");
+
+    let Some(expected) = expected else {
+        // Linking fails if the file exists, so the complete file appears at once, and
+        // only if there was none.
+        let written = write_temp(&temp, buf, None)?;
+        let linked = fs_hard_link(&temp, path);
+        fs_remove_file(&temp)?;
+        return match linked {
+            Ok(()) => Ok(written),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                Err(conflict(generation(path).ok()))
+            }
+            Err(e) => Err(e),
+        };
+    };
+
+    // Other writers using generations wait for the lock on the current file, so the
+    // check and the replacement are atomic with respect to them.
+    let mut file = loop {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(conflict(None)),
+            Err(e) => return Err(err(path, "open", e)),
+        };
+        file.lock().map_err(|e| err(path, "lock", e))?;
+        // The previous holder may have replaced the file after we opened it.
+        if is_current(path, &file) {
+            break file;
+        }
+    };
+    let (_, actual) = read_file(path, &mut file)?;
+    if actual != expected {
+        return Err(conflict(Some(actual)));
+    }
+    let permissions = file
+        .metadata()
+        .map_err(|e| err(path, "read metadata of", e))?
+        .permissions();
+    let written = write_temp(&temp, buf, Some(permissions))?;
+    fs_rename(&temp, path).inspect_err(|_| {
+        let _ = temp.rm();
+    })?;
+    Ok(written)
+}
+
+/// Write and sync the temp file, returning the generation it will have once renamed.
+fn write_temp(
+    temp: &Utf8Path,
+    buf: &[u8],
+    permissions: Option<std::fs::Permissions>,
+) -> io::Result<Generation> {
+    let write = || {
+        let mut file = File::options().write(true).create_new(true).open(temp)?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        file.write_all(buf)?;
+        file.sync_all()?;
+        Generation::of(&file.metadata()?, buf)
+    };
+    write().map_err(|e| {
+        let _ = temp.rm();
+        err(temp, "write to", e)
+    })
+}
+
+fn err(path: &Utf8Path, action: &str, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("Could not {action} {path} due to: {e}"))
+}
@@ -0,0 +1,140 @@
+use std::io;
+
+/// A glob pattern matched against paths with `/` as separator.
+///
+/// - `?` matches any character except `/`
+/// - `*` matches any number of characters except `/`
+/// - `**` matches any number of characters including `/`, and `**/` also matches nothing
+/// - `[abc]`, `[a-z]` and `[!abc]` match one character in, or not in, the set
+#[derive(Debug, Clone)]
+pub(crate) struct Glob {
+    pattern: Vec<char>,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: &str) -> io::Result<Self> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '[' {
+                match class_end(&chars, i) {
+                    Some(end) => i = end,
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("Glob \"{pattern}\" has an unclosed '['"),
+                        ))
+                    }
+                }
+            }
+            i += 1;
+        }
+        Ok(Self { pattern: chars })
+    }
+
+    /// Whether the whole path matches. Backslashes in the path are treated as `/`.
+    pub(crate) fn is_match(&self, path: &str) -> bool {
+        let path: Vec<char> = path
+            .chars()
+            .map(|c| if c == '\\' { '/' } else { c })
+            .collect();
+        Matcher::new(&self.pattern, &path).matches(0, 0)
+    }
+}
+
+/// Matches a pattern against a path, remembering the result for each pair of
+/// positions, so that patterns with many `*` take polynomial instead of exponential
+/// time when they don't match.
+struct Matcher<'a> {
+    p: &'a [char],
+    s: &'a [char],
+    memo: Vec<Option<bool>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(p: &'a [char], s: &'a [char]) -> Self {
+        Self {
+            p,
+            s,
+            memo: vec![None; (p.len() + 1) * (s.len() + 1)],
+        }
+    }
+
+    /// Whether `s[si..]` matches `p[pi..]`.
+    fn matches(&mut self, pi: usize, si: usize) -> bool {
+        let key = pi * (self.s.len() + 1) + si;
+        if let Some(matched) = self.memo[key] {
+            return matched;
+        }
+        let matched = self.matches_uncached(pi, si);
+        self.memo[key] = Some(matched);
+        matched
+    }
+
+    fn matches_uncached(&mut self, pi: usize, si: usize) -> bool {
+        let (p, s) = (self.p, self.s);
+        let c = s.get(si).copied();
+        match p.get(pi) {
+            None => c.is_none(),
+            Some('*') if p.get(pi + 1) == Some(&'*') => {
+                if p.get(pi + 2) == Some(&'/') {
+                    // `**/` matches nothing or any path ending with a `/`.
+                    self.matches(pi + 3, si)
+                        || (si..s.len()).any(|i| s[i] == '/' && self.matches(pi + 3, i + 1))
+                } else {
+                    (si..=s.len()).any(|i| self.matches(pi + 2, i))
+                }
+            }
+            Some('*') => {
+                for i in si..=s.len() {
+                    if self.matches(pi + 1, i) {
+                        return true;
+                    }
+                    if i < s.len() && s[i] == '/' {
+                        break;
+                    }
+                }
+                false
+            }
+            Some('?') => c.is_some_and(|c| c != '/') && self.matches(pi + 1, si + 1),
+            Some('[') => {
+                let end = class_end(p, pi).expect("validated in Glob::new");
+                c.is_some_and(|c| c != '/' && in_class(&p[pi + 1..end], c))
+                    && self.matches(end + 1, si + 1)
+            }
+            Some(&lit) => c == Some(lit) && self.matches(pi + 1, si + 1),
+        }
+    }
+}
+
+/// The index of the `]` closing the class starting at `start`.
+fn class_end(p: &[char], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    if matches!(p.get(i), Some('!' | '^')) {
+        i += 1;
+    }
+    // A `]` first in the class is a literal.
+    if p.get(i) == Some(&']') {
+        i += 1;
+    }
+    (i..p.len()).find(|&i| p[i] == ']')
+}
+
+fn in_class(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!' | '^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
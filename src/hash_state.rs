@@ -0,0 +1,137 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    time::UNIX_EPOCH,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    digest::file_digest,
+    escape::{percent_decode, percent_encode},
+    fs::{fs_metadata, fs_rename},
+    temp::sibling_temp_path,
+    AppendLog, Digest, Framing, SyncPolicy, Utf8PathExt,
+};
+
+/// A hashed file as stored in the state file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Hashed {
+    len: u64,
+    mtime: Option<(u64, u32)>,
+    digest: Digest,
+}
+
+pub(crate) fn hash_tree_with_state(
+    dir: &Utf8Path,
+    state_path: &Utf8Path,
+) -> io::Result<BTreeMap<Utf8PathBuf, Digest>> {
+    let known = load_state(state_path)?;
+    let mut log = AppendLog::open(state_path)?
+        .framing(Framing::Lines)
+        .sync_policy(SyncPolicy::Every(64));
+    let state_rel = state_path.strip_prefix(dir).ok();
+
+    let mut hashed = HashMap::new();
+    for rel in dir.ls().recurse().files().relative_paths().try_iter() {
+        let rel = rel?;
+        if Some(rel.as_path()) == state_rel {
+            continue;
+        }
+        let path = dir.join(&rel);
+        let md = fs_metadata(&path)?;
+        let mtime = md
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| (d.as_secs(), d.subsec_nanos()));
+
+        let entry = match known.get(&rel) {
+            Some(entry)
+                if entry.mtime.is_some() && (entry.len, entry.mtime) == (md.len(), mtime) =>
+            {
+                *entry
+            }
+            _ => {
+                let entry = Hashed {
+                    len: md.len(),
+                    mtime,
+                    digest: file_digest(&path)?,
+                };
+                log.append_record(format_record(&rel, &entry))?;
+                entry
+            }
+        };
+        hashed.insert(rel, entry);
+    }
+    drop(log);
+    // Leave out the files that were removed since the last run.
+    write_state(state_path, &hashed)?;
+
+    Ok(hashed
+        .into_iter()
+        .map(|(rel, entry)| (rel, entry.digest))
+        .collect())
+}
+
+fn load_state(path: &Utf8Path) -> io::Result<HashMap<Utf8PathBuf, Hashed>> {
+    let mut state = HashMap::new();
+    if !path.exists() {
+        return Ok(state);
+    }
+    let log = AppendLog::open(path)?.framing(Framing::Lines);
+    for record in log.records()? {
+        let record = record?;
+        // Later records replace earlier ones for the same file.
+        let (rel, entry) = parse_record(&record).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Could not parse hash state {path}"),
+            )
+        })?;
+        state.insert(rel, entry);
+    }
+    Ok(state)
+}
+
+fn write_state(path: &Utf8Path, state: &HashMap<Utf8PathBuf, Hashed>) -> io::Result<()> {
+    let mut sorted: Vec<_> = state.iter().collect();
+    sorted.sort_by_key(|(rel, _)| *rel);
+    let mut content = String::new();
+    for (rel, entry) in sorted {
+        content.push_str(&format_record(rel, entry));
+        content.push('\n');
+    }
+    let temp = sibling_temp_path(path, "hash-state");
+    temp.write(content)?;
+    fs_rename(&temp, path).inspect_err(|_| {
+        let _ = temp.rm();
+    })
+}
+
+/// `<digest> <len> <secs>.<nanos> <path>`, with `-` for an unknown modification time
+/// and newlines in the path percent-encoded.
+fn format_record(rel: &Utf8Path, entry: &Hashed) -> String {
+    let mtime = match entry.mtime {
+        Some((secs, nanos)) => format!("{secs}.{nanos:09}"),
+        None => "-".to_string(),
+    };
+    let rel = percent_encode(rel.as_str(), |b| b != b'\n' && b != b'%');
+    format!("{} {} {mtime} {rel}", entry.digest, entry.len)
+}
+
+fn parse_record(record: &[u8]) -> Option<(Utf8PathBuf, Hashed)> {
+    let record = std::str::from_utf8(record).ok()?;
+    let mut parts = record.splitn(4, ' ');
+    let digest = parts.next()?.parse().ok()?;
+    let len = parts.next()?.parse().ok()?;
+    let mtime = match parts.next()? {
+        "-" => None,
+        mtime => {
+            let (secs, nanos) = mtime.split_once('.')?;
+            Some((secs.parse().ok()?, nanos.parse().ok()?))
+        }
+    };
+    let rel = percent_decode(parts.next()?).ok()?;
+    Some((rel.into(), Hashed { len, mtime, digest }))
+}
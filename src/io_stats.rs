@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COUNTS: [AtomicU64; Counter::COUNT] = [const { AtomicU64::new(0) }; Counter::COUNT];
+
+#[derive(Clone, Copy)]
+pub(crate) enum Counter {
+    Reads,
+    BytesRead,
+    Writes,
+    BytesWritten,
+    Copies,
+    BytesCopied,
+    Renames,
+    FilesRemoved,
+    DirsCreated,
+    DirsRemoved,
+    MetadataCalls,
+    ReadDirCalls,
+}
+
+impl Counter {
+    const COUNT: usize = 12;
+}
+
+/// Add `n` to the counter if statistics are enabled.
+pub(crate) fn count(counter: Counter, n: u64) {
+    if ENABLED.load(Ordering::Relaxed) {
+        COUNTS[counter as usize].fetch_add(n, Ordering::Relaxed);
+    }
+}
+
+/// Process wide statistics of the file system operations done by this crate, e.g.
+/// to export them as metrics.
+///
+/// Collecting is off by default and has to be turned on with [`IoStats::enable`].
+/// Only the operations listed for each field are counted, not the ones done directly
+/// with `std::fs`, nor the many metadata reads done by e.g. `exists` or `is_dir`.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// IoStats::enable();
+/// let before = IoStats::snapshot();
+/// Utf8Path::new("Cargo.toml").read_bytes().unwrap();
+/// let stats = IoStats::snapshot();
+/// assert!(stats.reads > before.reads);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    /// Number of whole file reads, as by `read_bytes` and `read_string`.
+    pub reads: u64,
+    pub bytes_read: u64,
+    /// Number of whole file writes, as by `write`.
+    pub writes: u64,
+    pub bytes_written: u64,
+    /// Number of files copied, as by `cp`.
+    pub copies: u64,
+    pub bytes_copied: u64,
+    /// Number of renames, as by `mv`.
+    pub renames: u64,
+    /// Number of files removed one by one, as by `rm` on a file. The files in a
+    /// directory removed recursively are not counted.
+    pub files_removed: u64,
+    /// Number of directories created, including each missing parent.
+    pub dirs_created: u64,
+    /// Number of directories removed, where a directory removed recursively with all
+    /// its content counts as one.
+    pub dirs_removed: u64,
+    /// Number of metadata reads done through a [`Vfs`](crate::Vfs), as by `cp` and
+    /// `rm`. Metadata read in other ways, including by [`Ls`](crate::Ls), is not
+    /// counted.
+    pub metadata_calls: u64,
+    /// Number of directories read by [`Ls`](crate::Ls) walks.
+    pub read_dir_calls: u64,
+}
+
+impl IoStats {
+    /// Start collecting statistics.
+    pub fn enable() {
+        ENABLED.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop collecting statistics. The collected ones are kept.
+    pub fn disable() {
+        ENABLED.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled() -> bool {
+        ENABLED.load(Ordering::Relaxed)
+    }
+
+    /// The statistics collected so far.
+    pub fn snapshot() -> Self {
+        let get = |counter: Counter| COUNTS[counter as usize].load(Ordering::Relaxed);
+        Self {
+            reads: get(Counter::Reads),
+            bytes_read: get(Counter::BytesRead),
+            writes: get(Counter::Writes),
+            bytes_written: get(Counter::BytesWritten),
+            copies: get(Counter::Copies),
+            bytes_copied: get(Counter::BytesCopied),
+            renames: get(Counter::Renames),
+            files_removed: get(Counter::FilesRemoved),
+            dirs_created: get(Counter::DirsCreated),
+            dirs_removed: get(Counter::DirsRemoved),
+            metadata_calls: get(Counter::MetadataCalls),
+            read_dir_calls: get(Counter::ReadDirCalls),
+        }
+    }
+
+    /// Set all statistics to zero.
+    pub fn reset() {
+        for count in &COUNTS {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    ffi::CString,
+    fs, io, mem,
+    ops::Deref,
+    os::unix::{
+        fs::{FileTypeExt, MetadataExt},
+        net::{UnixListener, UnixStream},
+    },
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::fs::fs_remove_file;
+
+/// Create a named pipe at `path` with the permission bits in `mode`, which are
+/// reduced by the umask as usual.
+pub(crate) fn mkfifo(path: &Utf8Path, mode: u32) -> io::Result<()> {
+    let c_path = CString::new(path.as_str()).map_err(io::Error::other)?;
+    // SAFETY: `c_path` is a valid nul terminated string.
+    let ret = unsafe { libc::mkfifo(c_path.as_ptr(), mode as libc::mode_t) };
+    if ret != 0 {
+        let e = io::Error::last_os_error();
+        return Err(io::Error::new(
+            e.kind(),
+            format!("Could not create named pipe {path} due to: {e}"),
+        ));
+    }
+    Ok(())
+}
+
+/// A [`UnixListener`] bound to a socket file, which is removed again when this is dropped.
+///
+/// Created with [`Utf8PathExt::bind_unix_socket`](crate::Utf8PathExt::bind_unix_socket).
+/// Dereferences to the listener.
+#[derive(Debug)]
+pub struct BoundUnixSocket {
+    listener: UnixListener,
+    path: Utf8PathBuf,
+    // Identifies the socket file, so a socket that was replaced by another
+    // process isn't removed.
+    id: (u64, u64),
+}
+
+impl BoundUnixSocket {
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    pub fn listener(&self) -> &UnixListener {
+        &self.listener
+    }
+}
+
+impl Deref for BoundUnixSocket {
+    type Target = UnixListener;
+
+    fn deref(&self) -> &UnixListener {
+        &self.listener
+    }
+}
+
+impl Drop for BoundUnixSocket {
+    fn drop(&mut self) {
+        if socket_id(&self.path).is_some_and(|id| id == self.id) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+pub(crate) fn bind_unix_socket(path: &Utf8Path) -> io::Result<BoundUnixSocket> {
+    let max = max_socket_path_len();
+    if path.as_str().len() > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Could not bind to {path} as socket paths can be at most {max} bytes, it is {}",
+                path.as_str().len()
+            ),
+        ));
+    }
+    remove_stale_socket(path)?;
+
+    let listener = UnixListener::bind(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not bind to {path} due to: {e}")))?;
+    let id = socket_id(path)
+        .ok_or_else(|| io::Error::other(format!("Socket {path} disappeared after binding")))?;
+    Ok(BoundUnixSocket {
+        listener,
+        path: path.to_path_buf(),
+        id,
+    })
+}
+
+/// The longest path that fits in `sockaddr_un`, leaving room for the nul terminator.
+fn max_socket_path_len() -> usize {
+    // SAFETY: `sockaddr_un` is plain data for which all zeroes is a valid value.
+    let addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+    addr.sun_path.len() - 1
+}
+
+/// Remove a socket file that nobody listens on anymore, e.g. left by a process that crashed.
+/// Anything that isn't a socket is never removed.
+fn remove_stale_socket(path: &Utf8Path) -> io::Result<()> {
+    let Ok(md) = path.symlink_metadata() else {
+        return Ok(());
+    };
+    if !md.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Could not bind to {path} as it exists and is not a socket"),
+        ));
+    }
+    match UnixStream::connect(path) {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("Could not bind to {path} as it is already being listened on"),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs_remove_file(path),
+        Err(e) => Err(io::Error::new(
+            e.kind(),
+            format!("Could not check if socket {path} is in use due to: {e}"),
+        )),
+    }
+}
+
+fn socket_id(path: &Utf8Path) -> Option<(u64, u64)> {
+    let md = path.symlink_metadata().ok()?;
+    md.file_type().is_socket().then(|| (md.dev(), md.ino()))
+}
@@ -0,0 +1,82 @@
+use std::{fmt, fs::FileType, io};
+
+use camino::Utf8Path;
+
+/// The kind of a file system entry, see [`Utf8PathExt::file_kind`](crate::Utf8PathExt::file_kind).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    /// A Unix domain socket.
+    Socket,
+    /// A named pipe.
+    Fifo,
+    BlockDevice,
+    CharDevice,
+    /// Anything else, e.g. a Windows reparse point that is not a link.
+    Other,
+}
+
+impl FileKind {
+    /// Whether it is a socket, FIFO or device, which can't be copied by copying its bytes.
+    pub fn is_special(&self) -> bool {
+        matches!(
+            self,
+            Self::Socket | Self::Fifo | Self::BlockDevice | Self::CharDevice | Self::Other
+        )
+    }
+}
+
+impl From<FileType> for FileKind {
+    fn from(ft: FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+
+            if ft.is_socket() {
+                return Self::Socket;
+            } else if ft.is_fifo() {
+                return Self::Fifo;
+            } else if ft.is_block_device() {
+                return Self::BlockDevice;
+            } else if ft.is_char_device() {
+                return Self::CharDevice;
+            }
+        }
+        if ft.is_symlink() {
+            Self::Symlink
+        } else if ft.is_dir() {
+            Self::Dir
+        } else if ft.is_file() {
+            Self::File
+        } else {
+            Self::Other
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Dir => "directory",
+            Self::Symlink => "symlink",
+            Self::Socket => "socket",
+            Self::Fifo => "FIFO",
+            Self::BlockDevice => "block device",
+            Self::CharDevice => "character device",
+            Self::Other => "special file",
+        })
+    }
+}
+
+pub(crate) fn file_kind(path: &Utf8Path) -> io::Result<FileKind> {
+    let md = path.symlink_metadata().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read metadata of {path} due to: {e}"),
+        )
+    })?;
+    Ok(md.file_type().into())
+}
@@ -0,0 +1,129 @@
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    escape::{is_reserved_on_windows, percent_decode, percent_encode},
+    fs::*,
+    snapshot::fnv1a,
+    temp::sibling_temp_path,
+    Utf8PathExt,
+};
+
+/// The longest encoded key, which leaves room for the name of the temporary file
+/// within the 255 bytes that common file systems allow.
+const MAX_NAME_LEN: usize = 200;
+
+/// A small on-disk key-value store with a file per key, e.g. for caches.
+///
+/// The files are spread over 256 subdirectories by a hash of the key, so there are
+/// no huge flat directories. The key is percent-encoded in the file name, keeping
+/// only lowercase letters, digits, `-` and `_`, so that keys that only differ in
+/// case don't collide on case-insensitive file systems.
+///
+/// Values are written to a temporary file and renamed into place, so readers never
+/// see a partially written value.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let kv = FileKv::open(dir.join("cache")).unwrap();
+/// kv.put("https://example.com/a", "A").unwrap();
+/// assert_eq!(kv.get("https://example.com/a").unwrap().unwrap(), b"A");
+/// assert_eq!(kv.get("missing").unwrap(), None);
+///
+/// let keys: Vec<_> = kv.iter().map(|entry| entry.unwrap().0).collect();
+/// assert_eq!(keys, ["https://example.com/a"]);
+/// assert!(kv.remove("https://example.com/a").unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileKv {
+    root: Utf8PathBuf,
+}
+
+impl FileKv {
+    /// Open the store in the directory, creating it if it doesn't exist.
+    pub fn open<P: Into<Utf8PathBuf>>(root: P) -> io::Result<Self> {
+        let root = root.into();
+        root.mkdirs()?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    /// The file that the value of the key is stored in. Fails if the key is empty or
+    /// too long to be stored as a file name.
+    pub fn path(&self, key: &str) -> io::Result<Utf8PathBuf> {
+        let mut name = percent_encode(key, |b| {
+            b.is_ascii_lowercase() || b.is_ascii_digit() || matches!(b, b'-' | b'_')
+        });
+        if is_reserved_on_windows(&name) {
+            name = format!("%{:02X}{}", name.as_bytes()[0], &name[1..]);
+        }
+        if name.is_empty() || name.len() > MAX_NAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Could not use the key \"{key}\" as it is empty or too long"),
+            ));
+        }
+        let shard = format!("{:02x}", fnv1a(key.as_bytes()) as u8);
+        Ok(self.root.join(shard).join(name))
+    }
+
+    /// Store the value, replacing any existing value of the key.
+    pub fn put<B: AsRef<[u8]>>(&self, key: &str, value: B) -> io::Result<()> {
+        let path = self.path(key)?;
+        let temp = sibling_temp_path(&path, "put");
+        temp.write(value)?;
+        fs_rename(&temp, &path).inspect_err(|_| {
+            let _ = temp.rm();
+        })
+    }
+
+    /// The value of the key, or `None` if it isn't stored.
+    pub fn get(&self, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = self.path(key)?;
+        match std::fs::read(&path) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("Could not read {path} due to: {e}"),
+            )),
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.path(key).is_ok_and(|path| path.is_file())
+    }
+
+    /// Remove the key, returning whether it was stored.
+    pub fn remove(&self, key: &str) -> io::Result<bool> {
+        let path = self.path(key)?;
+        if !path.is_file() {
+            return Ok(false);
+        }
+        fs_remove_file(&path)?;
+        Ok(true)
+    }
+
+    /// The stored keys together with the files of their values, in no particular order.
+    /// Temporary files of writes in progress are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = io::Result<(String, Utf8PathBuf)>> {
+        self.root
+            .ls()
+            .recurse_if(|rel| rel.components().count() == 1)
+            .min_depth(2)
+            .files()
+            .skip_hidden()
+            .try_iter()
+            .map(|path| {
+                let path = path?;
+                let key = percent_decode(path.file_name().unwrap_or_default())?;
+                Ok((key, path))
+            })
+    }
+}
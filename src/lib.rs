@@ -1,17 +1,192 @@
+#[cfg(feature = "acl")]
+mod acl;
+mod anchored;
+mod append_log;
+#[cfg(feature = "tar")]
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_ext;
+mod backup;
+mod batch;
+#[cfg(feature = "clap")]
+pub mod clap;
+mod compress;
+mod cp;
+mod delta;
+mod diff;
+#[cfg(feature = "hash")]
+mod digest;
+mod display;
+#[cfg(any(feature = "include_dir", feature = "rust-embed"))]
+mod embed;
+mod eol;
+mod escape;
+mod expand;
+mod flags;
 mod fs;
+mod generation;
+mod glob;
+#[cfg(feature = "hash")]
+mod hash_state;
+mod io_stats;
+#[cfg(unix)]
+mod ipc;
+mod kind;
+mod kv;
+mod link;
+mod lock;
 mod ls;
+#[cfg(feature = "hash")]
+mod merkle;
+mod metadata;
+mod mount;
+mod natural;
+mod overlay;
+mod perms;
+mod platform;
+mod policy;
+mod portability;
+mod publish;
+mod quota;
+mod scratch;
+mod search;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod size;
+mod snapshot;
+mod space;
+#[cfg(feature = "tokio")]
+mod spawn;
+mod staged;
+mod stats;
+mod temp;
+mod testdir;
+mod vfs;
+#[cfg(any(target_os = "linux", all(target_os = "macos", feature = "xattr")))]
+mod xattr;
 
+#[cfg(feature = "acl")]
+pub use acl::Acl;
+pub use anchored::AnchoredPath;
+pub use append_log::{AppendLog, Framing, Records, SyncPolicy};
+#[cfg(feature = "tar")]
+pub use archive::{ArchiveEntry, ArchiveIndex, TarOptions};
+#[cfg(feature = "tokio")]
+pub use async_ext::AsyncUtf8PathExt;
+pub use batch::{exists_many, read_many};
+pub use compress::Codec;
+pub use cp::CpOptions;
+pub use delta::DeltaStats;
+pub use diff::{ChangedEntry, DiffEntry, DiffReport};
+#[cfg(feature = "hash")]
+pub use digest::{Digest, HashAlgorithm};
+#[cfg(feature = "include_dir")]
+pub use embed::materialize;
+#[cfg(feature = "rust-embed")]
+pub use embed::materialize_rust_embed;
+pub use eol::LineEnding;
+pub use escape::Shell;
+use flags::Flag;
 use fs::*;
-use ls::Ls;
-use std::{collections::VecDeque, io, iter, path::Path, time::SystemTime};
+pub use generation::{Generation, GenerationConflict};
+pub use io_stats::IoStats;
+#[cfg(unix)]
+pub use ipc::BoundUnixSocket;
+pub use kind::FileKind;
+pub use kv::FileKv;
+pub use lock::with_dir_lock;
+pub use ls::{ArcUtf8Path, Ls, LsEntry, TryLsIter};
+#[cfg(feature = "hash")]
+pub use merkle::MerkleNode;
+pub use metadata::Utf8Metadata;
+pub use overlay::OverlayFs;
+pub use perms::Permissions;
+pub use platform::{PathTooLong, Platform};
+pub use policy::{ExistingDirPolicy, OverwritePolicy, SpecialFilePolicy};
+pub use portability::{PortabilityIssue, PortabilityProblem, TargetFs};
+pub use quota::QuotaDir;
+pub use scratch::ScratchOverlay;
+pub use search::SearchPaths;
+pub use size::{ByteSize, SizeMode};
+pub use snapshot::DirSnapshot;
+#[cfg(feature = "tokio")]
+pub use spawn::Spawned;
+pub use staged::StagedWrite;
+pub use stats::{ExtensionUsage, TreeStats};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::FileTimes,
+    io, iter,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+pub use temp::{TempBuilder, Utf8TempDir, Utf8TempFile};
+pub use testdir::TestDir;
+pub use vfs::{FaultyFs, StdFs, Vfs};
 
-pub use camino::{Utf8Path, Utf8PathBuf};
+pub use camino::{FromOsStringError, FromPathBufError, Utf8Component, Utf8Path, Utf8PathBuf};
 
 pub trait Utf8PathBufExt {
     fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Utf8PathBuf>;
+
+    /// Convert a std path, replacing any invalid UTF-8 sequences with U+FFFD.
+    ///
+    /// Note that the resulting path may not point to the same file as the original.
+    fn from_path_lossy<P: AsRef<Path>>(path: P) -> Utf8PathBuf;
+
+    /// Convert a std path, returning an error holding the original `PathBuf`
+    /// if it is not valid UTF-8.
+    fn try_from_path<P: Into<PathBuf>>(path: P) -> Result<Utf8PathBuf, FromPathBufError>;
+
+    /// Convert an `OsString`, as returned by e.g. `std::env::var_os` or clap, returning
+    /// an error holding the original `OsString` if it is not valid UTF-8.
+    ///
+    /// It is not named `from_os_string`, as camino's inherent
+    /// `Utf8PathBuf::from_os_string` would always take precedence.
+    /// Use [`Utf8Path::as_os_str`] for the opposite direction.
+    fn try_from_os_string<S: Into<OsString>>(s: S) -> Result<Utf8PathBuf, FromOsStringError>;
+
+    /// Decode a percent-encoded path, as found in URLs, HTTP headers or sourcemaps.
+    ///
+    /// Fails if there are malformed `%` sequences or if the decoded path is not valid UTF-8.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8PathBuf::from_percent_encoded("my%20dir/file%231.txt").unwrap();
+    /// assert_eq!(path, "my dir/file#1.txt");
+    /// assert_eq!(path.percent_encode(), "my%20dir/file%231.txt");
+    /// ```
+    fn from_percent_encoded<S: AsRef<str>>(s: S) -> io::Result<Utf8PathBuf>;
+
+    /// Read the file on tokio's blocking thread pool.
+    #[cfg(feature = "tokio")]
+    fn spawn_read(self) -> Spawned<Vec<u8>>;
+
+    /// Read the file as a string on tokio's blocking thread pool.
+    #[cfg(feature = "tokio")]
+    fn spawn_read_string(self) -> Spawned<String>;
+
+    /// Write to the file on tokio's blocking thread pool. See [`Utf8PathExt::write`].
+    #[cfg(feature = "tokio")]
+    fn spawn_write<B: AsRef<[u8]> + Send + 'static>(self, buf: B) -> Spawned<()>;
+
+    /// Copy recursively on tokio's blocking thread pool. See [`Utf8PathExt::cp`].
+    #[cfg(feature = "tokio")]
+    fn spawn_cp<P: Into<Utf8PathBuf>>(self, to: P) -> Spawned<()>;
+
+    /// Rename on tokio's blocking thread pool. See [`Utf8PathExt::mv`].
+    #[cfg(feature = "tokio")]
+    fn spawn_mv<P: Into<Utf8PathBuf>>(self, to: P) -> Spawned<()>;
+
+    /// Remove the file or directory on tokio's blocking thread pool. See [`Utf8PathExt::rm`].
+    #[cfg(feature = "tokio")]
+    fn spawn_rm(self) -> Spawned<()>;
 }
 
 impl Utf8PathBufExt for Utf8PathBuf {
+    #[allow(clippy::io_other_error)]
     fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         Utf8PathBuf::from_path_buf(path.as_ref().to_path_buf()).map_err(|e| {
             io::Error::new(
@@ -20,6 +195,54 @@ impl Utf8PathBufExt for Utf8PathBuf {
             )
         })
     }
+
+    fn from_path_lossy<P: AsRef<Path>>(path: P) -> Self {
+        Utf8PathBuf::from(path.as_ref().to_string_lossy().into_owned())
+    }
+
+    fn try_from_path<P: Into<PathBuf>>(path: P) -> Result<Self, FromPathBufError> {
+        Utf8PathBuf::try_from(path.into())
+    }
+
+    fn try_from_os_string<S: Into<OsString>>(s: S) -> Result<Self, FromOsStringError> {
+        Utf8PathBuf::try_from(s.into())
+    }
+
+    fn from_percent_encoded<S: AsRef<str>>(s: S) -> io::Result<Self> {
+        escape::percent_decode(s.as_ref()).map(Utf8PathBuf::from)
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_read(self) -> Spawned<Vec<u8>> {
+        Spawned::new(move || self.read_bytes())
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_read_string(self) -> Spawned<String> {
+        Spawned::new(move || self.read_string())
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_write<B: AsRef<[u8]> + Send + 'static>(self, buf: B) -> Spawned<()> {
+        Spawned::new(move || self.write(buf))
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_cp<P: Into<Utf8PathBuf>>(self, to: P) -> Spawned<()> {
+        let to = to.into();
+        Spawned::new(move || self.cp(to))
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_mv<P: Into<Utf8PathBuf>>(self, to: P) -> Spawned<()> {
+        let to = to.into();
+        Spawned::new(move || self.mv(to))
+    }
+
+    #[cfg(feature = "tokio")]
+    fn spawn_rm(self) -> Spawned<()> {
+        Spawned::new(move || self.rm())
+    }
 }
 
 pub trait Utf8PathExt {
@@ -28,6 +251,36 @@ pub trait Utf8PathExt {
     /// This is really just a wrapper around Utf8Path's `strip_prefix` method.
     fn relative_to<P: AsRef<Path>>(&self, path: P) -> Option<&'_ Utf8Path>;
 
+    /// Returns the path relative to the first of the roots that it starts with,
+    /// together with the index of that root.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let roots = ["/overlay", "/base"];
+    /// let (idx, rel) = Utf8Path::new("/base/img/a.png").strip_any_prefix(roots).unwrap();
+    /// assert_eq!((idx, rel.as_str()), (1, "img/a.png"));
+    /// ```
+    fn strip_any_prefix<I, P>(&self, roots: I) -> Option<(usize, &'_ Utf8Path)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>;
+
+    /// Resolve a relative path against an anchor instead of the current working directory.
+    /// Absolute paths are returned unchanged.
+    ///
+    /// If the anchor is an existing directory the path is joined to it, otherwise the
+    /// anchor is taken to be a file, e.g. a config file, and the path is joined to
+    /// its parent directory.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("../assets/logo.png").resolve_from("/app/config/settings.toml");
+    /// assert_eq!(path, "/app/config/../assets/logo.png");
+    /// ```
+    fn resolve_from<P: AsRef<Utf8Path>>(&self, anchor: P) -> Utf8PathBuf;
+
     /// Add an extension to the path. If the path already has an extension, it is appended
     /// with the new extension.
     ///
@@ -41,6 +294,53 @@ pub trait Utf8PathExt {
     /// ```
     fn join_ext<S: AsRef<str>>(&self, ext: S) -> Utf8PathBuf;
 
+    /// Returns the path of a file next to this one, i.e. `self.parent().join(name)`.
+    ///
+    /// Fails if the path has no parent, e.g. `/`.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let meta = Utf8Path::new("src/foo.rs").sibling("foo.meta").unwrap();
+    /// assert_eq!(meta, "src/foo.meta");
+    /// ```
+    fn sibling<P: AsRef<Utf8Path>>(&self, name: P) -> io::Result<Utf8PathBuf>;
+
+    /// Returns the path with each component spelled the way it is on disk, e.g.
+    /// `src/MAIN.rs` becomes `src/main.rs` on a case-insensitive file system.
+    ///
+    /// Components are looked up by reading their parent directory, so this is slow
+    /// for large directories. Fails if a component doesn't exist.
+    fn actual_case(&self) -> io::Result<Utf8PathBuf>;
+
+    /// Returns the path with the component at `index`, as counted by
+    /// [`Utf8Path::components`], replaced by `new`.
+    ///
+    /// Fails if the path has no component at `index`.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("target/debug/app").replace_component(1, "release").unwrap();
+    /// assert_eq!(path, "target/release/app");
+    /// ```
+    fn replace_component<S: AsRef<str>>(&self, index: usize, new: S) -> io::Result<Utf8PathBuf>;
+
+    /// Returns the path with each normal component, i.e. not a root or prefix, `.` or
+    /// `..`, replaced by the result of `f`.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("/target/debug/debug.log")
+    ///     .map_components(|c| if c == "debug" { "release" } else { c });
+    /// assert_eq!(path, "/target/release/debug.log");
+    /// ```
+    fn map_components<'a, F, S>(&'a self, f: F) -> Utf8PathBuf
+    where
+        F: FnMut(&'a str) -> S,
+        S: AsRef<str>;
+
     /// Returns an iterator over the extensions of the path.
     fn extensions<'a>(&'a self) -> Box<dyn Iterator<Item = &'a str> + 'a>;
 
@@ -55,26 +355,364 @@ pub trait Utf8PathExt {
     /// Note that this is not performance optimized and may be slow for large directories.
     fn ls(&self) -> Ls;
 
+    /// Read the entries of the directory (non recursively) into a map keyed by file name.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let entries = Utf8Path::new("src").ls_map().unwrap();
+    /// assert!(entries["lib.rs"].is_file());
+    /// assert!(!entries.contains_key("main.rs"));
+    /// ```
+    fn ls_map(&self) -> io::Result<BTreeMap<String, LsEntry>>;
+
+    /// Walk the directory recursively and return the first entry that matches the
+    /// predicate, without reading the rest of the tree.
+    ///
+    /// The tree is walked level by level, so the match nearest to the directory is
+    /// returned. Entries on the same level are checked in natural order.
+    fn find_first<P: Fn(&Utf8Path) -> bool>(&self, predicate: P) -> Option<Utf8PathBuf>;
+
+    /// Walk the directory recursively and return the first entry with the given file
+    /// name, see [`Utf8PathExt::find_first`].
+    fn find_first_named<S: AsRef<str>>(&self, name: S) -> Option<Utf8PathBuf>;
+
+    /// Search the path and its ancestors for a directory that contains a file or
+    /// directory with the given name, and return that directory.
+    ///
+    /// Only the components of the path are searched, so make a relative path
+    /// absolute first to search all the way up to the root.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let root = Utf8Path::new(env!("CARGO_MANIFEST_DIR"));
+    /// let found = root.join("src/lib.rs").find_up("Cargo.toml");
+    /// assert_eq!(found.as_deref(), Some(root));
+    /// ```
+    fn find_up<S: AsRef<str>>(&self, name: S) -> Option<Utf8PathBuf>;
+
+    /// Returns an iterator over the path and its ancestors that exist on disk,
+    /// starting with the longest.
+    fn existing_ancestors<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Utf8Path> + 'a>;
+
+    /// The longest prefix of the path that exists on disk, i.e. the path itself if it
+    /// exists. Useful to tell where the missing part of a path starts.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("src/missing/file.txt");
+    /// assert_eq!(path.deepest_existing(), Some(Utf8Path::new("src")));
+    /// ```
+    fn deepest_existing(&self) -> Option<&Utf8Path>;
+
     /// Create directory if it does not exist.
     fn mkdir(&self) -> io::Result<()>;
 
     /// Create all directories if they don't exist.
     fn mkdirs(&self) -> io::Result<()>;
 
+    /// Create a named pipe (FIFO) at the path with the permission bits in `mode`,
+    /// e.g. `0o600`. The umask applies as for any new file.
+    ///
+    /// Fails with `AlreadyExists` if the path exists.
+    #[cfg(unix)]
+    fn mkfifo(&self, mode: u32) -> io::Result<()>;
+
+    /// Bind a Unix domain socket listener at the path. The socket file is removed when
+    /// the returned [`BoundUnixSocket`] is dropped.
+    ///
+    /// A socket file left behind by a process that is gone is removed first, while a
+    /// socket that is still listened on or a path that isn't a socket is an error.
+    /// Paths longer than the platform limit (107 bytes on Linux) fail with `InvalidInput`.
+    #[cfg(unix)]
+    fn bind_unix_socket(&self) -> io::Result<BoundUnixSocket>;
+
+    /// Create a Windows directory junction at the path that points to the directory `target`.
+    ///
+    /// Unlike directory symlinks, junctions can be created without special privileges.
+    /// Fails with an `Unsupported` error on other platforms.
+    fn create_junction<P: AsRef<Utf8Path>>(&self, target: P) -> io::Result<()>;
+
+    /// Whether the path is a Windows directory junction. Always false on other platforms.
+    fn is_junction(&self) -> bool;
+
+    /// Set or clear the Linux immutable attribute, like `chattr +i`. An immutable file
+    /// can't be changed, renamed or removed, not even by root.
+    ///
+    /// Usually requires root privileges. Fails with an `Unsupported` error on other platforms.
+    fn set_immutable(&self, immutable: bool) -> io::Result<()>;
+
+    /// Whether the path has the Linux immutable attribute. Always false on other platforms.
+    fn is_immutable(&self) -> bool;
+
+    /// Set or clear the Linux append-only attribute, like `chattr +a`. An append-only
+    /// file can only be opened for appending and can't be renamed or removed.
+    ///
+    /// Usually requires root privileges. Fails with an `Unsupported` error on other platforms.
+    fn set_append_only(&self, append_only: bool) -> io::Result<()>;
+
+    /// Whether the path has the Linux append-only attribute. Always false on other platforms.
+    fn is_append_only(&self) -> bool;
+
+    /// The permissions of the file or directory, following symlinks.
+    fn perms(&self) -> io::Result<Permissions>;
+
+    /// Set the permission bits, e.g. `0o755`, like `chmod`.
+    #[cfg(unix)]
+    fn set_mode(&self, mode: u32) -> io::Result<()>;
+
+    /// Set the permission bits of all files and directories in the tree, e.g.
+    /// `chmod_recursive(0o644, 0o755)` to make a deployed tree readable by everyone.
+    ///
+    /// Symlinks are not followed and left unchanged.
+    #[cfg(unix)]
+    fn chmod_recursive(&self, file_mode: u32, dir_mode: u32) -> io::Result<()>;
+
+    /// Set the owner and group ids, like `chown`. `None` leaves the id unchanged.
+    ///
+    /// Changing the owner usually requires root privileges.
+    #[cfg(unix)]
+    fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()>;
+
+    /// Set the owner and group ids of all files and directories in the tree, like
+    /// `chown -R`, e.g. to fix the ownership of an extracted archive.
+    ///
+    /// Symlinks are not followed, and their own ownership is changed.
+    #[cfg(unix)]
+    fn set_owner_recursive(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()>;
+
+    /// Make the file read-only or writable.
+    ///
+    /// On Unix, making it read-only removes the write permission for everyone, and
+    /// making it writable adds it for the owner only.
+    fn set_readonly(&self, readonly: bool) -> io::Result<()>;
+
+    /// Read the access control list, for when the permission bits aren't enough.
+    ///
+    /// Supports POSIX ACLs on Linux and the DACL on Windows, elsewhere this fails with
+    /// an `Unsupported` error. Use [`CpOptions::preserve_acls`] to copy them along.
+    #[cfg(feature = "acl")]
+    fn acl(&self) -> io::Result<Acl>;
+
+    /// Replace the access control list with one read by [`acl`](Self::acl).
+    ///
+    /// Usually requires owning the path.
+    #[cfg(feature = "acl")]
+    fn set_acl(&self, acl: &Acl) -> io::Result<()>;
+
+    /// The SELinux security context, e.g. `system_u:object_r:etc_t:s0`, or `None` if the
+    /// path has none, e.g. because SELinux isn't used.
+    ///
+    /// Use [`CpOptions::preserve_security_xattrs`] to copy it along.
+    #[cfg(target_os = "linux")]
+    fn security_context(&self) -> io::Result<Option<String>>;
+
+    /// The value of the extended attribute, e.g. `user.origin`, or `None` if it isn't
+    /// set or the file system doesn't support extended attributes.
+    ///
+    /// Use [`CpOptions::preserve_xattrs`] to copy them along.
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_get(&self, name: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Set the extended attribute, replacing any existing value.
+    ///
+    /// On Linux, unprivileged processes can only set attributes in the `user` namespace.
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_set<B: AsRef<[u8]>>(&self, name: &str, value: B) -> io::Result<()>;
+
+    /// The names of the extended attributes, empty if the file system doesn't support them.
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_list(&self) -> io::Result<Vec<String>>;
+
+    /// Remove the extended attribute. Does nothing if it isn't set.
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_remove(&self, name: &str) -> io::Result<()>;
+
     /// Remove the file or directory at the path.
     ///
+    /// Symlinks and junctions are removed without touching what they point to.
     /// Does nothing if the path does not exist.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// # #[cfg(unix)] {
+    /// let outside = TestDir::new().with_file("keep.txt", "data");
+    /// let tree = TestDir::new().with_file("sub/file.txt", "");
+    /// std::os::unix::fs::symlink(outside.path(), tree.join("sub/link")).unwrap();
+    ///
+    /// tree.join("sub").rm().unwrap();
+    /// tree.assert_tree(&[]);
+    /// outside.assert_file("keep.txt", "data");
+    /// # }
+    /// ```
     fn rm(&self) -> io::Result<()>;
 
+    /// Remove the file or directory at the path like [`Utf8PathExt::rm`], through the
+    /// given [`Vfs`] instead of the real file system.
+    fn rm_in(&self, fs: &dyn Vfs) -> io::Result<()>;
+
+    /// Remove the file or directory at the path like [`Utf8PathExt::rm`], but leave
+    /// directories on other file systems in place, like `rm -r --one-file-system`.
+    ///
+    /// Fails after removing everything else if something was left in place.
+    /// Only supported on Unix, elsewhere this is the same as [`Utf8PathExt::rm`].
+    fn rm_same_filesystem(&self) -> io::Result<()>;
+
+    /// Remove the file or directory at the path and then remove the parent directories
+    /// that became empty, up to but not including `stop_at`.
+    ///
+    /// Fails if `stop_at` is not an ancestor of the path.
+    fn rm_and_prune<P: AsRef<Utf8Path>>(&self, stop_at: P) -> io::Result<()>;
+
     /// Remove all files and directories in the directory recursively that match the predicate.
     fn rm_matching<P: Fn(&Utf8Path) -> bool>(&self, predicate: P) -> io::Result<()>;
 
     /// Copy recursively from the path to the destination path.
+    ///
+    /// A directory copied to an existing directory is merged into it, see
+    /// [`CpOptions::existing_dir`] for other options. A file copied to an existing
+    /// directory is placed inside the directory with its original name.
+    ///
+    /// Symlinks and junctions are copied as links, see [`CpOptions::follow_symlinks`].
     fn cp<P: Into<Utf8PathBuf>>(&self, to: P) -> io::Result<()>;
 
+    /// Copy recursively from the path to the destination path with the given options.
+    fn cp_with<P: Into<Utf8PathBuf>>(&self, to: P, options: &CpOptions) -> io::Result<()>;
+
+    /// Copy recursively like [`Utf8PathExt::cp_with`], through the given [`Vfs`]
+    /// instead of the real file system.
+    fn cp_in<P: Into<Utf8PathBuf>>(
+        &self,
+        fs: &dyn Vfs,
+        to: P,
+        options: &CpOptions,
+    ) -> io::Result<()>;
+
+    /// Update the file `dest` in place to the content of this file, only writing the
+    /// 64 KiB blocks that differ, e.g. to refresh a multi-GB disk image on a slow disk.
+    ///
+    /// Like rsync, blocks of the destination are found anywhere in the source with a
+    /// rolling checksum, so data after an insertion or removal is moved within the
+    /// destination instead of read from the source. The destination is created if it
+    /// doesn't exist and truncated to the length of the source.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let a: Vec<u8> = (0..64 * 1024).map(|i| (i * 7 % 251) as u8).collect();
+    /// let b: Vec<u8> = (0..64 * 1024).map(|i| (i * 13 % 241) as u8).collect();
+    /// let old = [&a[..], &b[..]].concat();
+    /// let new = [&a[..], b"inserted", &b[..]].concat();
+    ///
+    /// let dir = TestDir::new().with_file("new.img", &new).with_file("old.img", &old);
+    /// let stats = dir.join("new.img").delta_sync(dir.join("old.img")).unwrap();
+    /// dir.assert_file("old.img", &new);
+    /// assert_eq!(stats.unchanged, a.len() as u64);
+    /// assert_eq!(stats.moved, b.len() as u64);
+    /// assert_eq!(stats.written, 8);
+    /// ```
+    fn delta_sync<P: AsRef<Utf8Path>>(&self, dest: P) -> io::Result<DeltaStats>;
+
+    /// Back up the directory to a new snapshot in `snapshots_root` and return its path,
+    /// like `rsync --link-dest`.
+    ///
+    /// Each snapshot is a full copy of the directory named by the time in UTC, e.g.
+    /// `2024-05-01T13-45-10Z`. Files with the same length and modification time as in
+    /// the latest snapshot are hard linked to it instead of copied, so unchanged files
+    /// take no extra space. The snapshot only appears under its name when it is complete,
+    /// and fails without leaving a snapshot when an entry can't be read.
+    ///
+    /// Symlinks are copied as links and special files like sockets are left out.
+    fn backup_snapshot<P: AsRef<Utf8Path>>(&self, snapshots_root: P) -> io::Result<Utf8PathBuf>;
+
+    /// Publish the directory, e.g. a freshly built site, as the new version behind the
+    /// `live` symlink, for zero-downtime deployments.
+    ///
+    /// The directory is moved next to `live` under a name with the time in UTC, e.g.
+    /// `2024-05-01T13-45-10Z`, and `live` is atomically replaced by a symlink to it, so
+    /// readers see either the old or the new version. Returns the path of the version.
+    /// Old versions are kept, see [`gc_generations`](Self::gc_generations).
+    ///
+    /// On Windows, where creating symlinks needs privileges, `live` is a directory
+    /// junction instead, which is replaced with two renames, so it is missing for a
+    /// moment.
+    ///
+    /// ```no_run
+    /// use camino_fs::*;
+    ///
+    /// Utf8Path::new("build/site").publish_dir("/srv/site/current").unwrap();
+    /// ```
+    fn publish_dir<P: AsRef<Utf8Path>>(&self, live: P) -> io::Result<Utf8PathBuf>;
+
+    /// Remove the old versions in the directory, keeping the newest `keep` ones and
+    /// any that a symlink in the directory points to, like `current`. Returns the
+    /// removed versions.
+    ///
+    /// Only directories named by [`publish_dir`](Self::publish_dir) or
+    /// [`backup_snapshot`](Self::backup_snapshot), like `2024-05-01T13-45-10Z`, are
+    /// removed, so other entries in the directory are left alone.
+    fn gc_generations(&self, keep: usize) -> io::Result<Vec<Utf8PathBuf>>;
+
+    /// Write a tar archive of the directory's contents to `writer` and return the writer,
+    /// e.g. to stream it to a socket or through a compression encoder without a temp file.
+    ///
+    /// Paths in the archive are relative to the directory. Symlinks are archived as links.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let src = TestDir::new().with_file("a/b.txt", "b");
+    /// let archive = src.path().tar_to_writer(Vec::new()).unwrap();
+    ///
+    /// let dest = TestDir::new();
+    /// dest.path().untar_from_reader(archive.as_slice()).unwrap();
+    /// dest.assert_file("a/b.txt", "b");
+    /// ```
+    #[cfg(feature = "tar")]
+    fn tar_to_writer<W: io::Write>(&self, writer: W) -> io::Result<W>;
+
+    /// Like [`Utf8PathExt::tar_to_writer`] with the given options.
+    #[cfg(feature = "tar")]
+    fn tar_to_writer_with<W: io::Write>(&self, writer: W, options: &TarOptions) -> io::Result<W>;
+
+    /// Extract a tar archive read from `reader` into the directory, which is created if
+    /// it doesn't exist. Entries that would end up outside the directory are skipped.
+    #[cfg(feature = "tar")]
+    fn untar_from_reader<R: io::Read>(&self, reader: R) -> io::Result<()>;
+
     /// Renames a file or directory to a new name, replacing the original file if to already exists.
+    ///
+    /// When the destination is on another file system, the path is copied there with its
+    /// permissions and timestamps and then removed. If the copy fails, the partial copy is
+    /// removed and the source is left as is.
     fn mv<P: Into<Utf8PathBuf>>(&self, to: P) -> io::Result<()>;
 
+    /// Move the file or directory into `dir`, keeping its file name, and return the new path.
+    ///
+    /// The directory is created if it doesn't exist.
+    fn move_into<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Utf8PathBuf>;
+
+    /// Copy the file or directory recursively into `dir`, keeping its file name, and
+    /// return the new path.
+    ///
+    /// The directory is created if it doesn't exist. Fails with an `AlreadyExists` error
+    /// if the path is not a directory but `dir` holds a directory with its name, as it
+    /// would otherwise be copied into that directory.
+    fn copy_into<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Utf8PathBuf>;
+
+    /// Rename the file or directory within its parent directory and return the new path.
+    /// An existing file with the new name is replaced.
+    ///
+    /// Fails if `new_name` is not a plain file name.
+    fn rename_in_place<S: AsRef<str>>(&self, new_name: S) -> io::Result<Utf8PathBuf>;
+
+    /// Like [`Utf8PathExt::rename_in_place`] but fails with an `AlreadyExists` error
+    /// instead of replacing an existing file or directory.
+    fn rename_in_place_no_overwrite<S: AsRef<str>>(&self, new_name: S) -> io::Result<Utf8PathBuf>;
+
     /// Throw an error if the path does not exist.
     fn assert_exists(&self) -> io::Result<()>;
 
@@ -84,20 +722,339 @@ pub trait Utf8PathExt {
     /// Throw an error if the path is not a file.
     fn assert_file(&self) -> io::Result<()>;
 
+    /// Whether the directory has no entries. Only the first entry is read, so this is
+    /// cheap for large directories.
+    ///
+    /// Fails with a `NotADirectory` error if the path is a file, and `NotFound` if it
+    /// doesn't exist.
+    fn is_empty_dir(&self) -> io::Result<bool>;
+
     /// Write to the file at the path. Creates the file if it does not exist
     /// and replaces the content if it does.
     ///
     /// If the path also contains directories that do not exist, they will be created.
     fn write<B: AsRef<[u8]>>(&self, buf: B) -> io::Result<()>;
 
+    /// Write the file like [`Utf8PathExt::write`], through the given [`Vfs`] instead of
+    /// the real file system.
+    fn write_in<B: AsRef<[u8]>>(&self, fs: &dyn Vfs, buf: B) -> io::Result<()>;
+
+    /// Write the lines to the file, each followed by the given line ending.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let dir = TestDir::new();
+    /// let path = dir.join("lines.txt");
+    /// path.write_lines(["a", "b"], LineEnding::CrLf).unwrap();
+    /// assert_eq!(path.read_string().unwrap(), "a\r\nb\r\n");
+    /// ```
+    fn write_lines<I, S>(&self, lines: I, ending: LineEnding) -> io::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>;
+
+    /// Write the string to the file with its line endings converted to those of the
+    /// platform, see [`LineEnding::native`].
+    fn write_string_platform_eol<S: AsRef<str>>(&self, s: S) -> io::Result<()>;
+
     /// Read a file
     fn read_bytes(&self) -> io::Result<Vec<u8>>;
 
     /// Read a file as a string
     fn read_string(&self) -> io::Result<String>;
 
+    /// Read a file, decompressing it if it is compressed with one of the [`Codec`]s.
+    ///
+    /// The codec is detected by the magic bytes at the start of the file rather than
+    /// the extension, so rotated-compressed and live logs can be read alike. Plain
+    /// files are returned as they are.
+    fn read_bytes_auto(&self) -> io::Result<Vec<u8>>;
+
+    /// Like [`read_bytes_auto`](Self::read_bytes_auto), for UTF-8 contents.
+    fn read_string_auto(&self) -> io::Result<String>;
+
+    /// Compress the file to a new file with the extension of the codec added, e.g.
+    /// `app.log.1` to `app.log.1.gz`, and return the new path.
+    ///
+    /// The compressed file is written to a temporary file and renamed into place,
+    /// and the original is only removed once that succeeded. The permissions and
+    /// times of the original are kept. Fails if the compressed file already exists.
+    fn compress_in_place(&self, codec: Codec) -> io::Result<Utf8PathBuf>;
+
+    /// The counterpart of [`compress_in_place`](Self::compress_in_place), which
+    /// removes the extension of the codec detected from the contents.
+    fn decompress_in_place(&self) -> io::Result<Utf8PathBuf>;
+
     /// Get the system time for a file or folder
     fn mtime(&self) -> Option<SystemTime>;
+
+    /// The kind of the entry at the path, without following symlinks.
+    fn file_kind(&self) -> io::Result<FileKind>;
+
+    /// Whether the path is the root of a mounted file system, e.g. `/` or `/home` if it is
+    /// on a separate partition. On Linux bind mounts are detected as well.
+    ///
+    /// On Windows only the roots of volumes, such as `C:\`, are detected.
+    fn is_mount_point(&self) -> io::Result<bool>;
+
+    /// The mount point of the file system the path is on, i.e. the nearest ancestor of
+    /// the canonicalized path that is a mount point. Use it to not cross file system
+    /// boundaries, like `du -x`.
+    fn mount_point(&self) -> io::Result<Utf8PathBuf>;
+
+    /// Read the metadata including fields that are only available on some platforms,
+    /// such as the birth time, mount id and immutable attribute. On Linux they are
+    /// read with `statx`.
+    fn extended_metadata(&self) -> io::Result<Utf8Metadata>;
+
+    /// The current generation of the file, see [`Utf8PathExt::write_with_generation`].
+    fn generation(&self) -> io::Result<Generation>;
+
+    /// Read a file together with its generation, to be passed to
+    /// [`Utf8PathExt::write_with_generation`] when writing it back.
+    fn read_with_generation(&self) -> io::Result<(Vec<u8>, Generation)>;
+
+    /// Replace the content of the file, but only if it still has the `expected`
+    /// generation, or doesn't exist if `expected` is `None`. Returns the new generation.
+    ///
+    /// If the file was changed in the meantime, this fails with an error that wraps
+    /// a [`GenerationConflict`]. Concurrent calls for the same file are serialized
+    /// with a file lock, but writes by other means are only detected afterwards.
+    /// The new content is written to a temp file that replaces the file at once, so
+    /// readers never see a partial write.
+    ///
+    /// If the path also contains directories that do not exist, they will be created.
+    fn write_with_generation<B: AsRef<[u8]>>(
+        &self,
+        buf: B,
+        expected: Option<Generation>,
+    ) -> io::Result<Generation>;
+
+    /// Get the time since the file or folder was last modified. Modification times
+    /// in the future give a zero duration.
+    fn age(&self) -> Option<Duration>;
+
+    /// Returns true if the file or folder was modified within the given duration,
+    /// and false if it is older or doesn't exist.
+    fn modified_within(&self, duration: Duration) -> bool;
+
+    /// Like [`Utf8PathExt::age`], but relative to `now` instead of the current time,
+    /// so policies based on age can be tested deterministically.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    /// use std::time::Duration;
+    ///
+    /// let path = Utf8Path::new("Cargo.toml");
+    /// let now = path.mtime().unwrap() + Duration::from_secs(60);
+    /// assert_eq!(path.age_at(now), Some(Duration::from_secs(60)));
+    /// assert!(!path.modified_within_at(Duration::from_secs(59), now));
+    /// ```
+    fn age_at(&self, now: SystemTime) -> Option<Duration>;
+
+    /// Like [`Utf8PathExt::modified_within`], but relative to `now` instead of the
+    /// current time.
+    fn modified_within_at(&self, duration: Duration, now: SystemTime) -> bool;
+
+    /// Returns all files in the directory and its subdirectories that were modified
+    /// after the given time.
+    ///
+    /// This is a shorthand for `.ls().recurse().files().modified_since(time)`.
+    fn changed_since(&self, time: SystemTime) -> Ls;
+
+    /// Create an empty file at the path if nothing exists there, creating the parent
+    /// directories, or else set its access and modification times to now, like `touch`.
+    ///
+    /// The contents of an existing file are left unchanged.
+    fn touch(&self) -> io::Result<()>;
+
+    /// Set the modification time, leaving the access time unchanged.
+    fn set_mtime(&self, mtime: SystemTime) -> io::Result<()>;
+
+    /// Set the access and modification times, e.g. to restore them from a cache or
+    /// an archive.
+    fn set_times(&self, atime: SystemTime, mtime: SystemTime) -> io::Result<()>;
+
+    /// Set the modification time of the path and, for directories, of all files
+    /// and directories below it.
+    ///
+    /// The times are set by path, so unreadable files are touched as well, and
+    /// symlinks get the time themselves instead of what they point to, like `touch -h`.
+    ///
+    /// Useful for reproducible archives and for invalidating mtime-based build caches.
+    fn touch_recursive(&self, time: SystemTime) -> io::Result<()>;
+
+    /// Get the number of bytes available to the current user on the file system
+    /// containing the path. If the path doesn't exist, its closest existing ancestor is used.
+    fn free_space(&self) -> io::Result<ByteSize>;
+
+    /// Throw a `StorageFull` error if there are less than `bytes` available on the
+    /// file system containing the path.
+    fn ensure_free_space(&self, bytes: u64) -> io::Result<()>;
+
+    /// Summarize the number of files and bytes per extension for all files in the
+    /// directory and its subdirectories. Files without an extension are put under `None`.
+    /// Symlinks are skipped and not followed.
+    fn usage_by_extension(&self) -> io::Result<BTreeMap<Option<String>, ExtensionUsage>>;
+
+    /// Find the `n` largest files in the directory and its subdirectories, largest first.
+    ///
+    /// Only `n` entries are kept in memory during the walk, so this is suitable for huge trees.
+    /// Symlinks are skipped and not followed.
+    fn largest_files(&self, n: usize) -> io::Result<Vec<(Utf8PathBuf, ByteSize)>>;
+
+    /// Count the files, directories and symlinks in the directory, and find their
+    /// total size, largest file and newest modification time in a single walk.
+    ///
+    /// Symlinks are counted but not followed. Special files like sockets are counted
+    /// as files.
+    fn stat_tree(&self) -> io::Result<TreeStats>;
+
+    /// Compare this directory tree (old) with another one (new) and report the files
+    /// that were added, removed or changed. Files with the same size are compared byte by byte.
+    fn diff_dir<P: AsRef<Utf8Path>>(&self, other: P) -> io::Result<DiffReport>;
+
+    /// Like [`Utf8PathExt::diff_dir`] but compares files by their SHA-256 digest and
+    /// includes the digests in the report.
+    #[cfg(feature = "hash")]
+    fn diff_dir_hashed<P: AsRef<Utf8Path>>(&self, other: P) -> io::Result<DiffReport>;
+
+    /// Get the SHA-256 digest of the file content.
+    #[cfg(feature = "hash")]
+    fn digest(&self) -> io::Result<Digest>;
+
+    /// Get the SHA-256 digest of every file in the directory tree, keyed by the path
+    /// relative to the directory.
+    ///
+    /// The digests are recorded in `state_file` as they are computed, so when a run
+    /// is interrupted or repeated, only files that are new or have a changed size or
+    /// modification time are hashed again. The state file is skipped if it is inside
+    /// the directory.
+    #[cfg(feature = "hash")]
+    fn hash_tree_with_state<P: AsRef<Utf8Path>>(
+        &self,
+        state_file: P,
+    ) -> io::Result<BTreeMap<Utf8PathBuf, Digest>>;
+
+    /// Build a Merkle tree of the file or directory, where each directory has a digest
+    /// composed from its children, see [`MerkleNode`].
+    ///
+    /// Comparing the root digests of two trees, e.g. on different machines, tells if
+    /// they are equal, and exchanging the digests level by level finds what differs
+    /// without transferring the whole tree. Symlinks are hashed by their target.
+    #[cfg(feature = "hash")]
+    fn merkle_tree(&self, algo: HashAlgorithm) -> io::Result<MerkleNode>;
+
+    /// Get the size of the file, or the total size of everything in the directory,
+    /// like `du --apparent-size`.
+    ///
+    /// Symlinks inside the directory are not followed, and a file with several hard
+    /// links in the directory is counted once.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let src = Utf8Path::new("src");
+    /// assert!(src.size().unwrap() > src.join("lib.rs").size().unwrap());
+    /// ```
+    fn size(&self) -> io::Result<ByteSize>;
+
+    /// Like [`size`](Self::size), with the option to count the space allocated on
+    /// disk instead, like `du`.
+    fn size_with(&self, mode: SizeMode) -> io::Result<ByteSize>;
+
+    /// Like [`size_with`](Self::size_with), but leave out everything on another file
+    /// system than the path, like `du -x`, so that bind mounts, network mounts or
+    /// `/proc` are not traversed.
+    ///
+    /// Only supported on Unix, elsewhere this is the same as [`size_with`](Self::size_with).
+    fn size_same_filesystem(&self, mode: SizeMode) -> io::Result<ByteSize>;
+
+    /// Get the size of the file formatted in human readable units, e.g. "3.4 MiB".
+    /// Use [`size`](Self::size) for the size of a whole directory.
+    ///
+    /// See [`ByteSize`] for the formatting.
+    fn size_human(&self) -> io::Result<String>;
+
+    /// Percent-encode the path for use in URLs. All characters except the
+    /// unreserved ones (`A-Z a-z 0-9 - . _ ~`) and `/` are encoded.
+    fn percent_encode(&self) -> String;
+
+    /// Escape the characters in each component that aren't allowed on `platform`, e.g.
+    /// to replicate a Linux tree onto NTFS or exFAT with [`Platform::Windows`].
+    ///
+    /// The characters are percent-encoded, as is `%` itself, so the original path
+    /// can be restored with [`Utf8PathBufExt::from_percent_encoded`]. On Windows
+    /// this also covers trailing dots and spaces and reserved names like `CON`.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("notes/what?: 100%.txt");
+    /// let escaped = path.escape_for_target(Platform::Windows);
+    /// assert_eq!(escaped, "notes/what%3F%3A 100%25.txt");
+    /// assert_eq!(Utf8PathBuf::from_percent_encoded(&escaped).unwrap(), path);
+    ///
+    /// assert_eq!(Utf8Path::new("aux.log").escape_for_target(Platform::Windows), "%61ux.log");
+    /// ```
+    fn escape_for_target(&self, platform: Platform) -> Utf8PathBuf;
+
+    /// Find the entries in the directory that can't be copied as is to a `target` file
+    /// system, e.g. before copying to a USB stick. Checks names, file sizes, symlinks
+    /// and names that only differ in case.
+    ///
+    /// Symlinks are not followed. The issues are ordered as the tree is walked.
+    fn check_tree_portability(&self, target: TargetFs) -> io::Result<Vec<PortabilityIssue>>;
+
+    /// Quote the path for the native shell of the current platform, see [`Shell::native`].
+    ///
+    /// Paths that only contain safe characters are returned as is.
+    fn shell_quoted(&self) -> String;
+
+    /// Quote the path so it can be pasted as a single argument in a command for the given shell.
+    ///
+    /// Note that `cmd.exe` expands `%VAR%` even inside double quotes, so paths
+    /// containing `%` can't be quoted reliably for it.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let path = Utf8Path::new("my dir/it's.txt");
+    /// assert_eq!(path.shell_quoted_for(Shell::Sh), r"'my dir/it'\''s.txt'");
+    /// assert_eq!(path.shell_quoted_for(Shell::PowerShell), "'my dir/it''s.txt'");
+    /// assert_eq!(path.shell_quoted_for(Shell::Cmd), "\"my dir/it's.txt\"");
+    /// ```
+    fn shell_quoted_for(&self, shell: Shell) -> String;
+
+    /// Check that the path and each of its components are within the length limits of
+    /// the current platform, see [`Utf8PathExt::validate_length_for`].
+    fn validate_length(&self) -> io::Result<()>;
+
+    /// Check that the path and each of its components are within the length limits of
+    /// `platform`, e.g. before starting a copy that would otherwise fail halfway with
+    /// a "file name too long" error.
+    ///
+    /// Fails with `InvalidFilename` and a [`PathTooLong`] error inside. Only the
+    /// path as given is checked, so for a relative path the current directory will
+    /// add to the length.
+    fn validate_length_for(&self, platform: Platform) -> io::Result<()>;
+
+    /// Render the path for display with the home directory shortened to `~`,
+    /// e.g. `/home/user/project/x` becomes `~/project/x`.
+    fn display_compact(&self) -> String;
+
+    /// Like [`Utf8PathExt::display_compact`] but also replaces components in the middle
+    /// of the path with `…` so that the result is at most `max_width` characters,
+    /// e.g. `~/…/src/main.rs`.
+    fn display_compact_width(&self, max_width: usize) -> String;
+
+    /// Replace a leading `~` with the home directory of the current user.
+    fn expand_home(&self) -> io::Result<Utf8PathBuf>;
+
+    /// Replace `$VAR` and `${VAR}` with the value of the environment variable.
+    /// Fails if a variable is not set.
+    fn expand_env(&self) -> io::Result<Utf8PathBuf>;
 }
 
 impl Utf8PathExt for Utf8Path {
@@ -131,51 +1088,153 @@ impl Utf8PathExt for Utf8Path {
         Ok(())
     }
 
+    fn is_empty_dir(&self) -> io::Result<bool> {
+        let mut entries = self.read_dir_utf8().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not read directory {self} due to: {e}"),
+            )
+        })?;
+        Ok(entries.next().is_none())
+    }
+
     fn cp<P: Into<Utf8PathBuf>>(&self, to: P) -> io::Result<()> {
-        self.assert_exists()?;
-        let dest = to.into();
+        self.cp_with(to, &CpOptions::default())
+    }
 
-        if self.is_dir() {
-            self.assert_dir()?;
+    fn cp_with<P: Into<Utf8PathBuf>>(&self, to: P, options: &CpOptions) -> io::Result<()> {
+        cp::cp(&StdFs, self, &to.into(), options)
+    }
 
-            dest.mkdirs()?;
+    fn cp_in<P: Into<Utf8PathBuf>>(
+        &self,
+        fs: &dyn Vfs,
+        to: P,
+        options: &CpOptions,
+    ) -> io::Result<()> {
+        cp::cp(fs, self, &to.into(), options)
+    }
 
-            let mut entries: VecDeque<Utf8PathBuf> = self.ls().collect();
+    fn delta_sync<P: AsRef<Utf8Path>>(&self, dest: P) -> io::Result<DeltaStats> {
+        delta::delta_sync(self, dest.as_ref())
+    }
 
-            while let Some(src_path) = entries.pop_front() {
-                let rel_path = src_path.strip_prefix(self).unwrap();
-                let dest_path = dest.join(rel_path);
+    fn backup_snapshot<P: AsRef<Utf8Path>>(&self, snapshots_root: P) -> io::Result<Utf8PathBuf> {
+        backup::backup_snapshot(self, snapshots_root.as_ref())
+    }
 
-                if src_path.is_dir() {
-                    entries.extend(src_path.ls());
-                    dest_path.mkdir()?;
-                } else {
-                    fs_copy(&src_path, &dest_path)?;
-                }
-            }
-        } else {
-            fs_copy(self, &dest)?;
-        }
-        Ok(())
+    fn publish_dir<P: AsRef<Utf8Path>>(&self, live: P) -> io::Result<Utf8PathBuf> {
+        publish::publish_dir(self, live.as_ref())
+    }
+
+    fn gc_generations(&self, keep: usize) -> io::Result<Vec<Utf8PathBuf>> {
+        publish::gc_generations(self, keep)
+    }
+
+    #[cfg(feature = "tar")]
+    fn tar_to_writer<W: io::Write>(&self, writer: W) -> io::Result<W> {
+        self.tar_to_writer_with(writer, &TarOptions::default())
+    }
+
+    #[cfg(feature = "tar")]
+    fn tar_to_writer_with<W: io::Write>(&self, writer: W, options: &TarOptions) -> io::Result<W> {
+        archive::tar_to_writer(self, writer, options)
+    }
+
+    #[cfg(feature = "tar")]
+    fn untar_from_reader<R: io::Read>(&self, reader: R) -> io::Result<()> {
+        archive::untar_from_reader(self, reader)
     }
 
     fn mv<P: Into<Utf8PathBuf>>(&self, to: P) -> io::Result<()> {
         self.assert_exists()?;
-        fs_rename(self, &to.into())
+        let to = to.into();
+        match fs_rename(self, &to) {
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                cp::move_across_devices(self, &to)
+            }
+            res => res,
+        }
     }
 
     fn rm(&self) -> io::Result<()> {
-        if !self.exists() {
-            Ok(())
-        } else if self.is_dir() {
-            fs_remove_dir_all(self)
-        } else {
-            fs_remove_file(self)
+        vfs::rm(&StdFs, self)
+    }
+
+    fn rm_in(&self, fs: &dyn Vfs) -> io::Result<()> {
+        vfs::rm(fs, self)
+    }
+
+    fn rm_same_filesystem(&self) -> io::Result<()> {
+        mount::rm_same_filesystem(self)
+    }
+
+    fn move_into<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Utf8PathBuf> {
+        let dest = path_in_dir(self, dir.as_ref())?;
+        self.mv(&dest)?;
+        Ok(dest)
+    }
+
+    fn copy_into<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Utf8PathBuf> {
+        let dest = path_in_dir(self, dir.as_ref())?;
+        if !self.is_dir() && dest.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Could not copy {self} to {dest} as it is an existing directory"),
+            ));
+        }
+        self.cp(&dest)?;
+        Ok(dest)
+    }
+
+    fn rename_in_place<S: AsRef<str>>(&self, new_name: S) -> io::Result<Utf8PathBuf> {
+        let dest = renamed_path(self, new_name.as_ref())?;
+        self.mv(&dest)?;
+        Ok(dest)
+    }
+
+    fn rename_in_place_no_overwrite<S: AsRef<str>>(&self, new_name: S) -> io::Result<Utf8PathBuf> {
+        let dest = renamed_path(self, new_name.as_ref())?;
+        if dest.symlink_metadata().is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("Could not rename {self} to {dest} as it already exists"),
+            ));
+        }
+        self.mv(&dest)?;
+        Ok(dest)
+    }
+
+    fn rm_and_prune<P: AsRef<Utf8Path>>(&self, stop_at: P) -> io::Result<()> {
+        let stop_at = stop_at.as_ref();
+        if self == stop_at || !self.starts_with(stop_at) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path \"{stop_at}\" is not an ancestor of \"{self}\"!"),
+            ));
+        }
+        self.rm()?;
+        for dir in self.ancestors().skip(1) {
+            if dir == stop_at || !dir.is_dir() {
+                break;
+            }
+            let mut entries = dir.read_dir().map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not read directory {dir} due to: {e}"),
+                )
+            })?;
+            if entries.next().is_some() {
+                break;
+            }
+            fs_remove_dir(dir)?;
         }
+        Ok(())
     }
 
     fn rm_matching<P: Fn(&Utf8Path) -> bool>(&self, predicate: P) -> io::Result<()> {
-        if self.is_dir() {
+        // Never look inside a symlinked directory, as that would remove files outside the tree.
+        if self.symlink_metadata().is_ok_and(|md| md.is_dir()) {
             for file in self.ls().filter(|p| predicate(p)) {
                 file.rm()?;
             }
@@ -196,10 +1255,139 @@ impl Utf8PathExt for Utf8Path {
         fs_create_dir_all(self)
     }
 
+    #[cfg(unix)]
+    fn mkfifo(&self, mode: u32) -> io::Result<()> {
+        ipc::mkfifo(self, mode)
+    }
+
+    #[cfg(unix)]
+    fn bind_unix_socket(&self) -> io::Result<BoundUnixSocket> {
+        ipc::bind_unix_socket(self)
+    }
+
+    fn create_junction<P: AsRef<Utf8Path>>(&self, target: P) -> io::Result<()> {
+        link::create_junction(self, target.as_ref())
+    }
+
+    fn is_junction(&self) -> bool {
+        link::is_junction(self)
+    }
+
+    fn set_immutable(&self, immutable: bool) -> io::Result<()> {
+        flags::set(self, Flag::Immutable, immutable)
+    }
+
+    fn is_immutable(&self) -> bool {
+        flags::has(self, Flag::Immutable)
+    }
+
+    fn set_append_only(&self, append_only: bool) -> io::Result<()> {
+        flags::set(self, Flag::AppendOnly, append_only)
+    }
+
+    fn is_append_only(&self) -> bool {
+        flags::has(self, Flag::AppendOnly)
+    }
+
+    fn perms(&self) -> io::Result<Permissions> {
+        perms::perms(self)
+    }
+
+    #[cfg(unix)]
+    fn set_mode(&self, mode: u32) -> io::Result<()> {
+        perms::set_mode(self, mode)
+    }
+
+    #[cfg(unix)]
+    fn chmod_recursive(&self, file_mode: u32, dir_mode: u32) -> io::Result<()> {
+        perms::chmod_recursive(self, file_mode, dir_mode)
+    }
+
+    #[cfg(unix)]
+    fn set_owner(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+        perms::set_owner(self, uid, gid)
+    }
+
+    #[cfg(unix)]
+    fn set_owner_recursive(&self, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+        perms::set_owner_recursive(self, uid, gid)
+    }
+
+    fn set_readonly(&self, readonly: bool) -> io::Result<()> {
+        perms::set_readonly(self, readonly)
+    }
+
+    #[cfg(feature = "acl")]
+    fn acl(&self) -> io::Result<Acl> {
+        acl::read_acl(self)
+    }
+
+    #[cfg(feature = "acl")]
+    fn set_acl(&self, acl: &Acl) -> io::Result<()> {
+        acl::write_acl(self, acl)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn security_context(&self) -> io::Result<Option<String>> {
+        xattr::security_context(self)
+    }
+
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_get(&self, name: &str) -> io::Result<Option<Vec<u8>>> {
+        xattr::xattr_get(self, name)
+    }
+
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_set<B: AsRef<[u8]>>(&self, name: &str, value: B) -> io::Result<()> {
+        xattr::xattr_set(self, name, Some(value.as_ref()))
+    }
+
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_list(&self) -> io::Result<Vec<String>> {
+        xattr::xattr_list(self)
+    }
+
+    #[cfg(all(feature = "xattr", any(target_os = "linux", target_os = "macos")))]
+    fn xattr_remove(&self, name: &str) -> io::Result<()> {
+        xattr::xattr_set(self, name, None)
+    }
+
     fn ls(&self) -> Ls {
         Ls::new(self.to_path_buf())
     }
 
+    fn ls_map(&self) -> io::Result<BTreeMap<String, LsEntry>> {
+        ls::ls_map(self)
+    }
+
+    fn find_first<P: Fn(&Utf8Path) -> bool>(&self, predicate: P) -> Option<Utf8PathBuf> {
+        self.ls().recurse().sorted_natural().find(|p| predicate(p))
+    }
+
+    fn find_first_named<S: AsRef<str>>(&self, name: S) -> Option<Utf8PathBuf> {
+        let name = name.as_ref();
+        self.find_first(|p| p.file_name() == Some(name))
+    }
+
+    fn existing_ancestors<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Utf8Path> + 'a> {
+        Box::new(self.ancestors().filter(|p| p.exists()))
+    }
+
+    fn deepest_existing(&self) -> Option<&Utf8Path> {
+        self.existing_ancestors().next()
+    }
+
+    fn find_up<S: AsRef<str>>(&self, name: S) -> Option<Utf8PathBuf> {
+        let name = name.as_ref();
+        let dir = self.ancestors().find(|dir| dir.join(name).exists())?;
+        // The last ancestor of a relative path is empty.
+        Some(if dir.as_str().is_empty() {
+            Utf8PathBuf::from(".")
+        } else {
+            dir.to_path_buf()
+        })
+    }
+
     fn extensions<'a>(&'a self) -> Box<dyn Iterator<Item = &'a str> + 'a> {
         if let Some(name) = self.file_name() {
             Box::new(name.split('.').take(1))
@@ -218,6 +1406,63 @@ impl Utf8PathExt for Utf8Path {
         Utf8PathBuf::from(s)
     }
 
+    fn sibling<P: AsRef<Utf8Path>>(&self, name: P) -> io::Result<Utf8PathBuf> {
+        let parent = self.parent().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path \"{self}\" has no parent directory!"),
+            )
+        })?;
+        Ok(parent.join(name))
+    }
+
+    fn actual_case(&self) -> io::Result<Utf8PathBuf> {
+        let mut path = Utf8PathBuf::new();
+        for component in self.components() {
+            let Utf8Component::Normal(name) = component else {
+                path.push(component);
+                continue;
+            };
+            let dir = if path.as_str().is_empty() {
+                Utf8Path::new(".")
+            } else {
+                path.as_path()
+            };
+            let on_disk = actual_name(dir, name)?;
+            path.push(on_disk);
+        }
+        Ok(path)
+    }
+
+    fn replace_component<S: AsRef<str>>(&self, index: usize, new: S) -> io::Result<Utf8PathBuf> {
+        if index >= self.components().count() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path \"{self}\" has no component at index {index}!"),
+            ));
+        }
+        Ok(self
+            .components()
+            .enumerate()
+            .map(|(i, c)| if i == index { new.as_ref() } else { c.as_str() })
+            .collect())
+    }
+
+    fn map_components<'a, F, S>(&'a self, mut f: F) -> Utf8PathBuf
+    where
+        F: FnMut(&'a str) -> S,
+        S: AsRef<str>,
+    {
+        let mut path = Utf8PathBuf::new();
+        for component in self.components() {
+            match component {
+                Utf8Component::Normal(name) => path.push(f(name).as_ref()),
+                other => path.push(other),
+            }
+        }
+        path
+    }
+
     fn all_extensions(&self) -> Option<&str> {
         Some(self.file_name()?.split_once('.')?.1)
     }
@@ -226,11 +1471,51 @@ impl Utf8PathExt for Utf8Path {
         self.strip_prefix(path).ok()
     }
 
+    fn strip_any_prefix<I, P>(&self, roots: I) -> Option<(usize, &'_ Utf8Path)>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        roots
+            .into_iter()
+            .enumerate()
+            .find_map(|(idx, root)| Some((idx, self.strip_prefix(root).ok()?)))
+    }
+
+    fn resolve_from<P: AsRef<Utf8Path>>(&self, anchor: P) -> Utf8PathBuf {
+        let anchor = anchor.as_ref();
+        if self.is_absolute() {
+            self.to_path_buf()
+        } else if anchor.is_dir() {
+            anchor.join(self)
+        } else {
+            anchor.parent().unwrap_or(anchor).join(self)
+        }
+    }
+
     fn write<B: AsRef<[u8]>>(&self, buf: B) -> io::Result<()> {
-        if let Some(parent) = self.parent() {
-            parent.mkdirs()?;
+        vfs::write(&StdFs, self, buf.as_ref())
+    }
+
+    fn write_in<B: AsRef<[u8]>>(&self, fs: &dyn Vfs, buf: B) -> io::Result<()> {
+        vfs::write(fs, self, buf.as_ref())
+    }
+
+    fn write_lines<I, S>(&self, lines: I, ending: LineEnding) -> io::Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut s = String::new();
+        for line in lines {
+            s.push_str(line.as_ref());
+            s.push_str(ending.as_str());
         }
-        fs_write(self, buf.as_ref())
+        self.write(s)
+    }
+
+    fn write_string_platform_eol<S: AsRef<str>>(&self, s: S) -> io::Result<()> {
+        self.write(eol::with_line_endings(s.as_ref(), LineEnding::native()))
     }
 
     fn read_bytes(&self) -> io::Result<Vec<u8>> {
@@ -241,7 +1526,312 @@ impl Utf8PathExt for Utf8Path {
         fs_read_to_string(self)
     }
 
+    fn read_bytes_auto(&self) -> io::Result<Vec<u8>> {
+        compress::read_bytes_auto(self)
+    }
+
+    fn compress_in_place(&self, codec: Codec) -> io::Result<Utf8PathBuf> {
+        compress::compress_in_place(self, codec)
+    }
+
+    fn decompress_in_place(&self) -> io::Result<Utf8PathBuf> {
+        compress::decompress_in_place(self)
+    }
+
+    fn read_string_auto(&self) -> io::Result<String> {
+        String::from_utf8(self.read_bytes_auto()?).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Could not read {self} due to: {e}"),
+            )
+        })
+    }
+
+    fn generation(&self) -> io::Result<Generation> {
+        generation::generation(self)
+    }
+
+    fn read_with_generation(&self) -> io::Result<(Vec<u8>, Generation)> {
+        generation::read_with_generation(self)
+    }
+
+    fn write_with_generation<B: AsRef<[u8]>>(
+        &self,
+        buf: B,
+        expected: Option<Generation>,
+    ) -> io::Result<Generation> {
+        generation::write_with_generation(self, buf.as_ref(), expected)
+    }
+
     fn mtime(&self) -> Option<SystemTime> {
         self.metadata().ok().map(|md| md.modified().unwrap())
     }
+
+    fn file_kind(&self) -> io::Result<FileKind> {
+        kind::file_kind(self)
+    }
+
+    fn extended_metadata(&self) -> io::Result<Utf8Metadata> {
+        metadata::extended_metadata(self)
+    }
+
+    fn is_mount_point(&self) -> io::Result<bool> {
+        mount::is_mount_point(self)
+    }
+
+    fn mount_point(&self) -> io::Result<Utf8PathBuf> {
+        mount::mount_point(self)
+    }
+
+    fn age(&self) -> Option<Duration> {
+        self.age_at(SystemTime::now())
+    }
+
+    fn modified_within(&self, duration: Duration) -> bool {
+        self.modified_within_at(duration, SystemTime::now())
+    }
+
+    fn age_at(&self, now: SystemTime) -> Option<Duration> {
+        let mtime = self.mtime()?;
+        Some(now.duration_since(mtime).unwrap_or_default())
+    }
+
+    fn modified_within_at(&self, duration: Duration, now: SystemTime) -> bool {
+        self.age_at(now).is_some_and(|age| age <= duration)
+    }
+
+    fn changed_since(&self, time: SystemTime) -> Ls {
+        self.ls().recurse().files().modified_since(time)
+    }
+
+    fn touch(&self) -> io::Result<()> {
+        if !self.exists() {
+            if let Some(parent) = self.parent() {
+                parent.mkdirs()?;
+            }
+            // Another process may create the file in between, so it is never truncated.
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self)
+                .map_err(|e| {
+                    io::Error::new(e.kind(), format!("Could not create {self} due to: {e}"))
+                })?;
+            return Ok(());
+        }
+        let now = SystemTime::now();
+        fs_set_times(self, FileTimes::new().set_accessed(now).set_modified(now))
+    }
+
+    fn set_mtime(&self, mtime: SystemTime) -> io::Result<()> {
+        fs_set_times(self, FileTimes::new().set_modified(mtime))
+    }
+
+    fn set_times(&self, atime: SystemTime, mtime: SystemTime) -> io::Result<()> {
+        fs_set_times(
+            self,
+            FileTimes::new().set_accessed(atime).set_modified(mtime),
+        )
+    }
+
+    fn touch_recursive(&self, time: SystemTime) -> io::Result<()> {
+        self.assert_exists()?;
+        if self.is_dir() {
+            let base = self.to_path_buf();
+            let ls = self
+                .ls()
+                .recurse_if(move |rel| !base.join(rel).is_symlink());
+            for path in ls.try_iter() {
+                fs::set_mtime_nofollow(&path?, time)?;
+            }
+        }
+        fs::set_mtime_nofollow(self, time)
+    }
+
+    fn free_space(&self) -> io::Result<ByteSize> {
+        space::free_space(self).map(ByteSize)
+    }
+
+    fn ensure_free_space(&self, bytes: u64) -> io::Result<()> {
+        let available = space::free_space(self)?;
+        if available < bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::StorageFull,
+                format!(
+                    "Not enough free space for {self}: {} needed but only {} available",
+                    ByteSize(bytes),
+                    ByteSize(available)
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn usage_by_extension(&self) -> io::Result<BTreeMap<Option<String>, ExtensionUsage>> {
+        stats::usage_by_extension(self)
+    }
+
+    fn largest_files(&self, n: usize) -> io::Result<Vec<(Utf8PathBuf, ByteSize)>> {
+        stats::largest_files(self, n)
+    }
+
+    fn stat_tree(&self) -> io::Result<TreeStats> {
+        stats::stat_tree(self)
+    }
+
+    fn diff_dir<P: AsRef<Utf8Path>>(&self, other: P) -> io::Result<DiffReport> {
+        diff::diff_dirs(self, other.as_ref(), false)
+    }
+
+    #[cfg(feature = "hash")]
+    fn diff_dir_hashed<P: AsRef<Utf8Path>>(&self, other: P) -> io::Result<DiffReport> {
+        diff::diff_dirs(self, other.as_ref(), true)
+    }
+
+    #[cfg(feature = "hash")]
+    fn digest(&self) -> io::Result<Digest> {
+        digest::file_digest(self)
+    }
+
+    #[cfg(feature = "hash")]
+    fn hash_tree_with_state<P: AsRef<Utf8Path>>(
+        &self,
+        state_file: P,
+    ) -> io::Result<BTreeMap<Utf8PathBuf, Digest>> {
+        hash_state::hash_tree_with_state(self, state_file.as_ref())
+    }
+
+    #[cfg(feature = "hash")]
+    fn merkle_tree(&self, algo: HashAlgorithm) -> io::Result<MerkleNode> {
+        merkle::merkle_tree(self, algo)
+    }
+
+    fn size(&self) -> io::Result<ByteSize> {
+        self.size_with(SizeMode::Apparent)
+    }
+
+    fn size_with(&self, mode: SizeMode) -> io::Result<ByteSize> {
+        size::tree_size(self, mode, false).map(ByteSize)
+    }
+
+    fn size_same_filesystem(&self, mode: SizeMode) -> io::Result<ByteSize> {
+        size::tree_size(self, mode, true).map(ByteSize)
+    }
+
+    fn size_human(&self) -> io::Result<String> {
+        let len = fs_metadata(self)?.len();
+        Ok(ByteSize(len).to_string())
+    }
+
+    fn percent_encode(&self) -> String {
+        escape::percent_encode(self.as_str(), |b| b == b'/' || escape::is_unreserved(b))
+    }
+
+    fn check_tree_portability(&self, target: TargetFs) -> io::Result<Vec<PortabilityIssue>> {
+        portability::check_tree_portability(self, target)
+    }
+
+    fn escape_for_target(&self, platform: Platform) -> Utf8PathBuf {
+        let names: Vec<String> = self
+            .as_str()
+            .split('/')
+            .map(|name| escape::escape_name_for(name, platform))
+            .collect();
+        Utf8PathBuf::from(names.join("/"))
+    }
+
+    fn shell_quoted(&self) -> String {
+        Shell::native().quote(self.as_str())
+    }
+
+    fn shell_quoted_for(&self, shell: Shell) -> String {
+        shell.quote(self.as_str())
+    }
+
+    fn validate_length(&self) -> io::Result<()> {
+        platform::validate_length(self, Platform::native())
+    }
+
+    fn validate_length_for(&self, platform: Platform) -> io::Result<()> {
+        platform::validate_length(self, platform)
+    }
+
+    fn display_compact(&self) -> String {
+        display::compact(self, None)
+    }
+
+    fn display_compact_width(&self, max_width: usize) -> String {
+        display::compact(self, Some(max_width))
+    }
+
+    fn expand_home(&self) -> io::Result<Utf8PathBuf> {
+        expand::expand_home(self.as_str()).map(Utf8PathBuf::from)
+    }
+
+    fn expand_env(&self) -> io::Result<Utf8PathBuf> {
+        expand::expand_env(self.as_str()).map(Utf8PathBuf::from)
+    }
+}
+
+/// The path `dir/<file name of path>`, creating `dir` if needed.
+fn path_in_dir(path: &Utf8Path, dir: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    path.assert_exists()?;
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Path \"{path}\" has no file name!"),
+        )
+    })?;
+    dir.mkdirs()?;
+    Ok(dir.join(name))
+}
+
+/// The name of the entry in `dir` that matches `name`, preferring an exact match
+/// over a case-insensitive one.
+fn actual_name(dir: &Utf8Path, name: &str) -> io::Result<String> {
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read directory {dir} due to: {e}"),
+        )
+    };
+    let lower = name.to_lowercase();
+    let mut found = None;
+    for entry in dir.read_dir_utf8().map_err(err)? {
+        let entry = entry.map_err(err)?;
+        let entry_name = entry.file_name();
+        if entry_name == name {
+            return Ok(name.to_string());
+        }
+        if found.is_none() && entry_name.to_lowercase() == lower {
+            found = Some(entry_name.to_string());
+        }
+    }
+    found.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Path \"{}\" does not exist!", dir.join(name)),
+        )
+    })
+}
+
+/// The path with the file name replaced by `new_name`, which must be a plain file name.
+fn renamed_path(path: &Utf8Path, new_name: &str) -> io::Result<Utf8PathBuf> {
+    let mut components = Utf8Path::new(new_name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Utf8Component::Normal(_)), None)
+    ) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("\"{new_name}\" is not a valid file name!"),
+        ));
+    }
+    if path.file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Path \"{path}\" has no file name!"),
+        ));
+    }
+    Ok(path.with_file_name(new_name))
 }
@@ -0,0 +1,184 @@
+use std::io;
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+#[cfg(windows)]
+use crate::fs::fs_create_dir;
+use crate::Vfs;
+
+/// Whether the path is a Windows directory junction. Always false on other platforms.
+#[cfg(windows)]
+pub(crate) fn is_junction(path: &Utf8Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::{
+        Foundation::INVALID_HANDLE_VALUE,
+        Storage::FileSystem::{
+            FindClose, FindFirstFileW, FILE_ATTRIBUTE_REPARSE_POINT, WIN32_FIND_DATAW,
+        },
+    };
+
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA0000003;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    // SAFETY: `wide` is nul terminated and `data` is only read after a successful call.
+    unsafe {
+        let mut data: WIN32_FIND_DATAW = std::mem::zeroed();
+        let handle = FindFirstFileW(wide.as_ptr(), &mut data);
+        if handle == INVALID_HANDLE_VALUE {
+            return false;
+        }
+        FindClose(handle);
+        // For reparse points the reparse tag is stored in `dwReserved0`.
+        data.dwFileAttributes & FILE_ATTRIBUTE_REPARSE_POINT != 0
+            && data.dwReserved0 == IO_REPARSE_TAG_MOUNT_POINT
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn is_junction(_path: &Utf8Path) -> bool {
+    false
+}
+
+/// Create a directory junction at `link` that points to the directory `target`.
+#[cfg(windows)]
+pub(crate) fn create_junction(link: &Utf8Path, target: &Utf8Path) -> io::Result<()> {
+    use std::os::windows::{fs::OpenOptionsExt, io::AsRawHandle};
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+
+    const FSCTL_SET_REPARSE_POINT: u32 = 0x000900A4;
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA0000003;
+    const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x02000000;
+    const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x00200000;
+
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not create junction {link} to {target} due to: {e}"),
+        )
+    };
+    // Junctions must point to an absolute path.
+    let target = std::path::absolute(target).map_err(err)?;
+    let print_name: Vec<u16> = target.as_os_str().encode_wide().collect();
+    let substitute_name: Vec<u16> = "\\??\\"
+        .encode_utf16()
+        .chain(print_name.iter().copied())
+        .collect();
+
+    // REPARSE_DATA_BUFFER with a MountPointReparseBuffer: both names are nul terminated.
+    let substitute_len = (substitute_name.len() * 2) as u16;
+    let print_len = (print_name.len() * 2) as u16;
+    let data_len = 8 + substitute_len + 2 + print_len + 2;
+    let mut buf: Vec<u8> = Vec::with_capacity(8 + data_len as usize);
+    buf.extend(IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    buf.extend(data_len.to_le_bytes());
+    buf.extend(0u16.to_le_bytes());
+    buf.extend(0u16.to_le_bytes()); // substitute name offset
+    buf.extend(substitute_len.to_le_bytes());
+    buf.extend((substitute_len + 2).to_le_bytes()); // print name offset
+    buf.extend(print_len.to_le_bytes());
+    for c in substitute_name
+        .iter()
+        .chain(&[0])
+        .chain(&print_name)
+        .chain(&[0])
+    {
+        buf.extend(c.to_le_bytes());
+    }
+
+    fs_create_dir(link)?;
+    let result = std::fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(link)
+        .and_then(|dir| {
+            let mut returned = 0u32;
+            // SAFETY: the handle is open for writing and `buf` is a valid reparse buffer.
+            let ok = unsafe {
+                DeviceIoControl(
+                    dir.as_raw_handle() as _,
+                    FSCTL_SET_REPARSE_POINT,
+                    buf.as_ptr() as _,
+                    buf.len() as u32,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    if let Err(e) = result {
+        let _ = std::fs::remove_dir(link);
+        return Err(err(e));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+pub(crate) fn create_junction(link: &Utf8Path, _target: &Utf8Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Could not create junction {link} as junctions only exist on Windows"),
+    ))
+}
+
+/// Recreate the symlink or junction at `src` as `dest`, pointing to the same target.
+pub(crate) fn copy_link(src: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    let target = std::fs::read_link(src)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read link {src} due to: {e}")))?;
+    let target = Utf8PathBuf::from_path_buf(target)
+        .map_err(|p| io::Error::other(format!("Link target {p:?} of {src} is not valid UTF-8")))?;
+    if is_junction(src) {
+        return create_junction(dest, &target);
+    }
+    symlink(src, &target, dest).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not create link {dest} due to: {e}"),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn symlink(_src: &Utf8Path, target: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, dest)
+}
+
+#[cfg(windows)]
+fn symlink(src: &Utf8Path, target: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file, FileTypeExt};
+
+    if src.symlink_metadata()?.file_type().is_symlink_dir() {
+        symlink_dir(target, dest)
+    } else {
+        symlink_file(target, dest)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_src: &Utf8Path, _target: &Utf8Path, _dest: &Utf8Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Symlinks are not supported on this platform",
+    ))
+}
+
+/// Remove a symlink or junction without touching what it points to.
+pub(crate) fn remove_link(fs: &dyn Vfs, path: &Utf8Path, md: &std::fs::Metadata) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+        // Links to directories are directories themselves on Windows.
+        if md.file_attributes() & FILE_ATTRIBUTE_DIRECTORY != 0 {
+            return fs.remove_dir(path);
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = md;
+    fs.remove_file(path)
+}
@@ -0,0 +1,93 @@
+use std::{fs::File, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Utf8PathExt;
+
+const LOCK_FILE: &str = ".lock";
+
+/// Run `f` while holding an exclusive advisory lock on the `.lock` file in `dir`,
+/// blocking until other processes (or threads) holding the lock are done.
+///
+/// The directory is created if it doesn't exist. The lock is released when `f`
+/// returns or panics. On unix the lock file is removed again, elsewhere it is left
+/// in place as it can't be removed safely while other processes may be waiting for it.
+///
+/// The lock is advisory: it only guards against code that also uses `with_dir_lock`.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let count = with_dir_lock(dir.path(), || {
+///     let path = dir.join("count");
+///     let count = path.read_string().unwrap_or_default().parse().unwrap_or(0) + 1;
+///     path.write(count.to_string())?;
+///     Ok::<_, std::io::Error>(count)
+/// })
+/// .unwrap()
+/// .unwrap();
+/// assert_eq!(count, 1);
+/// ```
+pub fn with_dir_lock<P: AsRef<Utf8Path>, R, F: FnOnce() -> R>(dir: P, f: F) -> io::Result<R> {
+    let dir = dir.as_ref();
+    dir.mkdirs()?;
+    let _lock = DirLock::acquire(dir.join(LOCK_FILE))?;
+    Ok(f())
+}
+
+struct DirLock {
+    path: Utf8PathBuf,
+    file: File,
+}
+
+impl DirLock {
+    fn acquire(path: Utf8PathBuf) -> io::Result<Self> {
+        loop {
+            let file = File::options()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("Could not open lock file {path} due to: {e}"),
+                    )
+                })?;
+            file.lock().map_err(|e| {
+                io::Error::new(e.kind(), format!("Could not lock {path} due to: {e}"))
+            })?;
+            // The previous holder may have removed the file after we opened it, in which
+            // case we locked a file that no one else will see.
+            if is_current(&path, &file) {
+                return Ok(Self { path, file });
+            }
+        }
+    }
+}
+
+impl Drop for DirLock {
+    fn drop(&mut self) {
+        // Removing the file while still holding the lock makes waiters retry with a new file.
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.path);
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn is_current(path: &Utf8Path, file: &File) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (path.metadata(), file.metadata()) {
+        (Ok(on_disk), Ok(locked)) => on_disk.dev() == locked.dev() && on_disk.ino() == locked.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_current(_path: &Utf8Path, _file: &File) -> bool {
+    true
+}
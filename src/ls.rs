@@ -1,22 +1,144 @@
-use std::{collections::VecDeque, io};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fs::{FileType, Metadata},
+    io,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread,
+    time::SystemTime,
+};
 
 use camino::{Utf8Path, Utf8PathBuf};
 
+use crate::{
+    glob::Glob,
+    io_stats::{count, Counter},
+    mount::{device_id, metadata_device_id},
+    natural::natural_cmp,
+};
+
+/// A reference counted path, see [`Ls::shared`].
+pub type ArcUtf8Path = Arc<Utf8Path>;
+
+/// A directory entry together with its metadata.
+///
+/// The entries of [`Ls::entries`] follow symlinks like the [`Ls::files`] and
+/// [`Ls::dirs`] filters do, so the metadata of a link to a file is that of the file,
+/// while [`LsEntry::is_symlink`] still tells that it is a link. Dangling links keep
+/// the metadata of the link itself. The entries of
+/// [`Utf8PathExt::ls_map`](crate::Utf8PathExt::ls_map) don't follow symlinks.
+#[derive(Debug, Clone)]
+pub struct LsEntry {
+    path: Utf8PathBuf,
+    metadata: Metadata,
+    symlink: bool,
+}
+
+impl LsEntry {
+    fn new(path: Utf8PathBuf, metadata: Metadata) -> Self {
+        let symlink = metadata.is_symlink();
+        Self {
+            path,
+            metadata,
+            symlink,
+        }
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    pub fn into_path(self) -> Utf8PathBuf {
+        self.path
+    }
+
+    pub fn file_name(&self) -> &str {
+        self.path.file_name().unwrap_or_default()
+    }
+
+    pub fn file_type(&self) -> FileType {
+        self.metadata.file_type()
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.metadata.is_file()
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.symlink
+    }
+}
+
 enum LsFilter {
     All,
     Files,
     Dirs,
 }
 
-pub struct Ls {
-    recurse_if_fn: Box<dyn Fn(&Utf8Path) -> bool>,
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LsOrder {
+    BreadthFirst,
+    DepthFirst,
+    PostOrder,
+}
+
+/// A directory listing, see [`Utf8PathExt::ls`](crate::Utf8PathExt::ls).
+///
+/// The type parameter is the recurse predicate, which is `Send` unless one that
+/// isn't was given to [`Ls::recurse_if_local`].
+pub struct Ls<P: ?Sized = dyn Fn(&Utf8Path) -> bool + Send> {
+    recurse_if_fn: Box<P>,
     relative_paths: bool,
     path: Utf8PathBuf,
     filter: LsFilter,
+    order: LsOrder,
+    skip_hidden: bool,
+    sorted_natural: bool,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    modified_since: Option<SystemTime>,
+    glob: Option<Glob>,
+    #[cfg(feature = "regex")]
+    regex: Option<regex::Regex>,
+    same_filesystem_only: bool,
+    root_device: Option<u64>,
     initialized: bool,
-    entries: VecDeque<Utf8PathBuf>,
+    /// The entries to visit, with whether the directory's entries were already
+    /// queued, which is only the case for post-order directories.
+    entries: VecDeque<(LsEntry, bool)>,
+    pending_err: Option<io::Error>,
+    #[cfg(feature = "rayon")]
+    par: Option<ParWalk>,
+    /// Whether directories are read by [`Ls::stream`] instead of by the walk itself.
+    #[cfg(feature = "tokio")]
+    read_async: bool,
+    /// The directory to read before the walk can continue, see [`Ls::stream`].
+    #[cfg(feature = "tokio")]
+    unread_dir: Option<Utf8PathBuf>,
+}
+
+/// The directory listings read on the thread pool, see [`Ls::par_iter`].
+#[cfg(feature = "rayon")]
+struct ParWalk {
+    tx: mpsc::Sender<DirListing>,
+    rx: Receiver<DirListing>,
+    /// The number of directories being read.
+    pending: usize,
 }
 
+/// The entries of a directory, and the first error reading them.
+type DirListing = (Vec<LsEntry>, Option<io::Error>);
+
 impl Ls {
     pub fn new(path: Utf8PathBuf) -> Self {
         Self {
@@ -24,11 +146,31 @@ impl Ls {
             relative_paths: false,
             path,
             filter: LsFilter::All,
+            order: LsOrder::BreadthFirst,
+            skip_hidden: false,
+            sorted_natural: false,
+            min_depth: 0,
+            max_depth: None,
+            modified_since: None,
+            glob: None,
+            #[cfg(feature = "regex")]
+            regex: None,
+            same_filesystem_only: false,
+            root_device: None,
             initialized: false,
             entries: VecDeque::new(),
+            pending_err: None,
+            #[cfg(feature = "rayon")]
+            par: None,
+            #[cfg(feature = "tokio")]
+            read_async: false,
+            #[cfg(feature = "tokio")]
+            unread_dir: None,
         }
     }
+}
 
+impl<P: Fn(&Utf8Path) -> bool + ?Sized> Ls<P> {
     /// If true, the iterator returns relative paths instead of absolute paths.
     ///
     /// This is especially useful for copying or moving files.
@@ -40,13 +182,25 @@ impl Ls {
     /// Only recurse into directories that satisfy the given predicate, which is
     /// given a path that is always **relative** to the base path. In other words
     /// this is not changed by the _.relative_paths()_ function/setting.
-    pub fn recurse_if<P: Fn(&Utf8Path) -> bool + 'static>(mut self, predicate: P) -> Self {
-        self.recurse_if_fn = Box::new(predicate);
-        self
+    ///
+    pub fn recurse_if<Q>(self, predicate: Q) -> Ls
+    where
+        Q: Fn(&Utf8Path) -> bool + Send + 'static,
+    {
+        self.with_recurse_if(Box::new(predicate))
+    }
+
+    /// Like [`Ls::recurse_if`] with a predicate that isn't `Send`, e.g. one holding an
+    /// `Rc`. The walk can then not be moved to another thread.
+    pub fn recurse_if_local<Q>(self, predicate: Q) -> Ls<dyn Fn(&Utf8Path) -> bool>
+    where
+        Q: Fn(&Utf8Path) -> bool + 'static,
+    {
+        self.with_recurse_if(Box::new(predicate))
     }
 
     /// Recurse into all directories.
-    pub fn recurse(self) -> Self {
+    pub fn recurse(self) -> Ls {
         self.recurse_if(|_| true)
     }
 
@@ -66,109 +220,596 @@ impl Ls {
         }
     }
 
-    /// An iterator where you have to handle errors yourself.
+    /// When recursing, return the entries of a directory right after the directory
+    /// itself, instead of level by level.
+    pub fn depth_first(mut self) -> Self {
+        self.order = LsOrder::DepthFirst;
+        self
+    }
+
+    /// When recursing, return a directory after all of its entries, e.g. to remove
+    /// a tree or to add up the sizes of directories bottom up.
+    pub fn post_order(mut self) -> Self {
+        self.order = LsOrder::PostOrder;
+        self
+    }
+
+    /// Skip hidden entries and don't recurse into hidden directories. On Windows these
+    /// are the entries with the hidden attribute and elsewhere the names starting with `.`.
+    pub fn skip_hidden(mut self) -> Self {
+        self.skip_hidden = true;
+        self
+    }
+
+    /// Sort the entries of each directory so that numbers are ordered by value,
+    /// e.g. `file2` before `file10`.
     ///
-    /// Set all options before calling this function.
-    pub fn try_iter(self) -> TryLsIter {
-        TryLsIter::new(self)
+    /// When recursing, the directories are still listed level by level unless
+    /// [`Ls::depth_first`] is used, so only the entries within each directory are sorted.
+    pub fn sorted_natural(mut self) -> Self {
+        self.sorted_natural = true;
+        self
     }
 
-    fn add_dir_entries(entries: &mut VecDeque<Utf8PathBuf>, dir: &Utf8Path) {
-        let Ok(new_entries) = dir.read_dir_utf8() else {
-            return;
-        };
+    /// Only return entries at least `depth` levels below the base, where the entries
+    /// directly in the base are at depth 1. E.g. `min_depth(2)` with
+    /// [`Ls::recurse`] skips the direct children.
+    pub fn min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = depth;
+        self
+    }
 
-        entries.extend(new_entries.filter_map(|e| e.ok().map(|e| e.into_path())))
+    /// Don't recurse deeper than `depth` levels below the base, where the entries
+    /// directly in the base are at depth 1. This limits [`Ls::recurse`] and
+    /// [`Ls::recurse_if`] and doesn't turn on recursion by itself.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
     }
-}
 
-impl Iterator for Ls {
-    type Item = Utf8PathBuf;
+    /// Only return entries with a modification time after the given time.
+    pub fn modified_since(mut self, time: SystemTime) -> Self {
+        self.modified_since = Some(time);
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if !self.initialized {
-            Self::add_dir_entries(&mut self.entries, &self.path);
-            self.initialized = true;
+    /// Only return entries whose path relative to the base matches the glob pattern,
+    /// e.g. `**/*.rs`. A later pattern replaces an earlier one.
+    ///
+    /// The pattern doesn't affect which directories are walked, so use it together
+    /// with [`Ls::recurse`] for patterns that span directories. Supports `?`, `*`,
+    /// `**` and character classes like `[a-z]` and `[!0-9]`.
+    ///
+    /// An invalid pattern makes the walk return no entries, and [`Ls::try_iter`]
+    /// returns the error.
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let files: Vec<_> = Utf8Path::new("src").ls().recurse().files().glob("**/l?.rs").collect();
+    /// assert_eq!(files, vec![Utf8Path::new("src/ls.rs")]);
+    /// ```
+    pub fn glob(mut self, pattern: &str) -> Self {
+        match Glob::new(pattern) {
+            Ok(glob) => self.glob = Some(glob),
+            Err(e) => {
+                self.pending_err = Some(e);
+                self.initialized = true;
+            }
         }
+        self
+    }
 
-        while let Some(mut path) = self.entries.pop_front() {
-            let rel_path = path.strip_prefix(&self.path).unwrap();
+    /// Only return entries whose path relative to the base matches the regex, with `/`
+    /// as separator on all platforms. Use `^` and `$` to match the whole path.
+    ///
+    /// Like [`Ls::glob`] this doesn't affect which directories are walked.
+    #[cfg(feature = "regex")]
+    pub fn matching_regex(mut self, regex: regex::Regex) -> Self {
+        self.regex = Some(regex);
+        self
+    }
+
+    /// Don't descend into directories on another file system than the listed
+    /// directory, so that bind mounts, network mounts or `/proc` are not traversed,
+    /// like `find -xdev`. The mount points themselves are still listed.
+    ///
+    /// Only supported on Unix, elsewhere this has no effect.
+    pub fn same_filesystem_only(mut self) -> Self {
+        self.same_filesystem_only = true;
+        self
+    }
+
+    /// Yield the entries as reference counted paths that are cheap to clone and
+    /// can be shared across threads, e.g. when storing millions of entries in indexes.
+    pub fn shared(self) -> impl Iterator<Item = ArcUtf8Path> {
+        self.map(ArcUtf8Path::from)
+    }
 
-            if path.is_dir() && (self.recurse_if_fn)(rel_path) {
-                Self::add_dir_entries(&mut self.entries, &path);
+    /// Run the walk on a background thread and receive the entries through a channel
+    /// holding at most `bound` entries, so that reading directories overlaps with
+    /// processing the entries.
+    ///
+    /// The thread stops when the walk is done or the receiver is dropped.
+    pub fn spawn_into_channel(self, bound: usize) -> Receiver<Utf8PathBuf>
+    where
+        Self: Send + 'static,
+    {
+        let (tx, rx) = mpsc::sync_channel(bound);
+        thread::spawn(move || {
+            for path in self {
+                if tx.send(path).is_err() {
+                    break;
+                }
             }
-            let is_file = path.is_file();
-            let is_dir = path.is_dir();
+        });
+        rx
+    }
+
+    /// Collect the entries into a map from extension to paths. Entries without an
+    /// extension, such as most directories, are put under `None`.
+    ///
+    /// Combine with `.files()` to leave out directories.
+    pub fn group_by_extension(self) -> BTreeMap<Option<String>, Vec<Utf8PathBuf>> {
+        let mut groups: BTreeMap<Option<String>, Vec<Utf8PathBuf>> = BTreeMap::new();
+        for path in self {
+            groups
+                .entry(path.extension().map(str::to_string))
+                .or_default()
+                .push(path);
+        }
+        groups
+    }
 
-            if self.relative_paths {
-                path = rel_path.to_path_buf();
+    /// Yield the entries together with their metadata, which is read once while
+    /// listing the directory, so there is no need to stat every path again.
+    ///
+    /// The paths follow [`Ls::relative_paths`]. Like the paths iterator, entries
+    /// that can't be read are skipped, and symlinks are followed, see [`LsEntry`].
+    ///
+    /// ```
+    /// use camino_fs::*;
+    ///
+    /// let total: u64 = Utf8Path::new("src")
+    ///     .ls()
+    ///     .recurse()
+    ///     .files()
+    ///     .entries()
+    ///     .map(|entry| entry.metadata().len())
+    ///     .sum();
+    /// assert!(total > 0);
+    /// ```
+    pub fn entries(mut self) -> impl Iterator<Item = LsEntry> {
+        std::iter::from_fn(move || loop {
+            match self.next_entry()? {
+                Ok(entry) => return Some(entry),
+                Err(_) => continue,
             }
-            match self.filter {
-                LsFilter::All => return Some(path),
-                LsFilter::Files if is_file => return Some(path),
-                LsFilter::Dirs if is_dir => return Some(path),
-                _ => {}
+        })
+    }
+
+    /// Like [`Ls::entries`], but returning the errors like [`Ls::try_iter`].
+    pub fn try_entries(mut self) -> impl Iterator<Item = io::Result<LsEntry>> {
+        std::iter::from_fn(move || self.next_entry())
+    }
+
+    /// Walk the subdirectories in parallel on the rayon thread pool, which is much
+    /// faster for large trees like `node_modules` or `target`.
+    ///
+    /// The directories are read on the pool and their entries sent back through a
+    /// channel, where the filters are applied. The entries come in no particular
+    /// order, so [`Ls::depth_first`], [`Ls::post_order`] and [`Ls::sorted_natural`]
+    /// have no effect. Like the plain iterator, entries that can't be read are skipped.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter(mut self) -> impl Iterator<Item = Utf8PathBuf> {
+        let (tx, rx) = mpsc::channel();
+        self.order = LsOrder::BreadthFirst;
+        self.sorted_natural = false;
+        self.par = Some(ParWalk { tx, rx, pending: 0 });
+        self
+    }
+
+    /// Walk the directories asynchronously with `tokio::fs`, so that async servers can
+    /// list trees without blocking the runtime.
+    ///
+    /// All options apply like for the iterator, and like [`Ls::try_iter`] the stream
+    /// returns the errors. Only symlinks, and the base directory when using
+    /// [`Ls::same_filesystem_only`], are checked with blocking calls.
+    ///
+    /// The stream is `Send` unless a predicate that isn't was given to
+    /// [`Ls::recurse_if_local`].
+    ///
+    /// ```
+    /// use camino_fs::*;
+    /// use futures_util::StreamExt;
+    ///
+    /// # tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(async {
+    /// let files: Vec<_> = Utf8Path::new("src").ls().files().stream().collect().await;
+    /// assert!(files.iter().any(|file| file.as_ref().unwrap() == "src/ls.rs"));
+    /// # });
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn stream(mut self) -> impl futures_util::Stream<Item = io::Result<Utf8PathBuf>> {
+        self.read_async = true;
+        futures_util::stream::unfold(self, |mut ls| async move {
+            loop {
+                if let Some(entry) = ls.next_entry() {
+                    return Some((entry.map(LsEntry::into_path), ls));
+                }
+                let dir = ls.unread_dir.take()?;
+                let (new_entries, err) = read_dir_entries_async(&dir, ls.skip_hidden).await;
+                ls.queue_entries(new_entries);
+                if let Some(e) = err {
+                    ls.pending_err = Some(e);
+                }
             }
+        })
+    }
+
+    /// An iterator where you have to handle errors yourself.
+    ///
+    /// Set all options before calling this function.
+    pub fn try_iter(self) -> TryLsIter<P> {
+        TryLsIter { ls: self }
+    }
+
+    /// The same walk with another recurse predicate, which may change whether it is `Send`.
+    fn with_recurse_if<Q: ?Sized>(self, recurse_if_fn: Box<Q>) -> Ls<Q> {
+        let Ls {
+            recurse_if_fn: _,
+            relative_paths,
+            path,
+            filter,
+            order,
+            skip_hidden,
+            sorted_natural,
+            min_depth,
+            max_depth,
+            modified_since,
+            glob,
+            #[cfg(feature = "regex")]
+            regex,
+            same_filesystem_only,
+            root_device,
+            initialized,
+            entries,
+            pending_err,
+            #[cfg(feature = "rayon")]
+            par,
+            #[cfg(feature = "tokio")]
+            read_async,
+            #[cfg(feature = "tokio")]
+            unread_dir,
+        } = self;
+        Ls {
+            recurse_if_fn,
+            relative_paths,
+            path,
+            filter,
+            order,
+            skip_hidden,
+            sorted_natural,
+            min_depth,
+            max_depth,
+            modified_since,
+            glob,
+            #[cfg(feature = "regex")]
+            regex,
+            same_filesystem_only,
+            root_device,
+            initialized,
+            entries,
+            pending_err,
+            #[cfg(feature = "rayon")]
+            par,
+            #[cfg(feature = "tokio")]
+            read_async,
+            #[cfg(feature = "tokio")]
+            unread_dir,
         }
-        None
     }
-}
 
-pub struct TryLsIter {
-    ls: Ls,
-    initialized: bool,
-    entries: VecDeque<Utf8PathBuf>,
-}
+    /// Queue the entries of the directory. Entries that can't be read are skipped
+    /// and the first such error is returned after queueing the others.
+    fn add_dir_entries(&mut self, dir: &Utf8Path) -> io::Result<()> {
+        #[cfg(feature = "rayon")]
+        if let Some(par) = &mut self.par {
+            let (dir, tx, skip_hidden) = (dir.to_path_buf(), par.tx.clone(), self.skip_hidden);
+            par.pending += 1;
+            rayon::spawn(move || {
+                // Fails only when the iterator was dropped.
+                let _ = tx.send(read_dir_entries(&dir, skip_hidden));
+            });
+            return Ok(());
+        }
+        #[cfg(feature = "tokio")]
+        if self.read_async {
+            self.unread_dir = Some(dir.to_path_buf());
+            return Ok(());
+        }
+        let (new_entries, first_err) = read_dir_entries(dir, self.skip_hidden);
+        self.queue_entries(new_entries);
+        first_err.map_or(Ok(()), Err)
+    }
 
-impl TryLsIter {
-    fn new(ls: Ls) -> Self {
-        Self {
-            ls,
-            initialized: false,
-            entries: Default::default(),
+    fn queue_entries(&mut self, mut new_entries: Vec<LsEntry>) {
+        if self.sorted_natural {
+            new_entries.sort_by(|a, b| natural_cmp(a.path.as_str(), b.path.as_str()));
+        }
+        let new_entries = new_entries.into_iter().map(|entry| (entry, false));
+        match self.order {
+            LsOrder::BreadthFirst => self.entries.extend(new_entries),
+            LsOrder::DepthFirst | LsOrder::PostOrder => {
+                for entry in new_entries.rev() {
+                    self.entries.push_front(entry);
+                }
+            }
+        }
+    }
+
+    /// The next entry to visit, waiting for the directories being read in parallel.
+    fn pop_entry(&mut self) -> Option<(LsEntry, bool)> {
+        // The stream reads the directory before the walk continues, which keeps the order.
+        #[cfg(feature = "tokio")]
+        if self.unread_dir.is_some() {
+            return None;
         }
+        #[cfg(feature = "rayon")]
+        while self.entries.is_empty() {
+            let par = self.par.as_mut().filter(|par| par.pending > 0)?;
+            let (new_entries, err) = par.rx.recv().ok()?;
+            par.pending -= 1;
+            if let Some(e) = err {
+                self.pending_err.get_or_insert(e);
+            }
+            self.queue_entries(new_entries);
+        }
+        self.entries.pop_front()
     }
 
-    fn add_dir_entries(entries: &mut VecDeque<Utf8PathBuf>, dir: &Utf8Path) -> io::Result<()> {
-        for entry in dir.read_dir_utf8()? {
-            entries.push_back(entry?.into_path());
+    #[cfg(feature = "regex")]
+    fn matches_regex(&self, rel_path: &Utf8Path) -> bool {
+        let Some(regex) = &self.regex else {
+            return true;
+        };
+        if cfg!(windows) {
+            regex.is_match(&rel_path.as_str().replace('\\', "/"))
+        } else {
+            regex.is_match(rel_path.as_str())
         }
-        Ok(())
     }
 
-    fn try_next_unfiltered(&mut self) -> io::Result<Option<Utf8PathBuf>> {
+    #[cfg(not(feature = "regex"))]
+    fn matches_regex(&self, _rel_path: &Utf8Path) -> bool {
+        true
+    }
+
+    fn matches_mtime(&self, entry: &LsEntry) -> bool {
+        let Some(since) = self.modified_since else {
+            return true;
+        };
+        // Symlinks are already followed, so one that still is has no target.
+        !entry.metadata.is_symlink() && entry.metadata.modified().is_ok_and(|mtime| mtime > since)
+    }
+
+    /// The next entry that passes the filter, or an error if reading a directory failed.
+    fn next_entry(&mut self) -> Option<io::Result<LsEntry>> {
+        if let Some(e) = self.pending_err.take() {
+            return Some(Err(e));
+        }
         if !self.initialized {
-            Self::add_dir_entries(&mut self.entries, &self.ls.path)?;
             self.initialized = true;
+            if self.same_filesystem_only {
+                self.root_device = device_id(&self.path);
+            }
+            let path = self.path.clone();
+            if let Err(e) = self.add_dir_entries(&path) {
+                return Some(Err(e));
+            }
         }
-        while let Some(mut path) = self.entries.pop_front() {
-            let rel_path = path.strip_prefix(&self.ls.path).unwrap();
 
-            if path.is_dir() && (self.ls.recurse_if_fn)(&rel_path) {
-                Self::add_dir_entries(&mut self.entries, &path)?;
+        while let Some((mut entry, expanded)) = self.pop_entry() {
+            let other_device = self.root_device.is_some()
+                && metadata_device_id(&entry.metadata) != self.root_device;
+            // Symlinks are followed, so only they need another stat.
+            if entry.metadata.is_symlink() {
+                if let Ok(metadata) = entry.path.metadata() {
+                    entry.metadata = metadata;
+                }
             }
-            if self.ls.relative_paths {
-                path = rel_path.to_path_buf();
+            let (is_dir, is_file) = (entry.is_dir(), entry.is_file());
+            let path = &entry.path;
+            let rel_path = path.strip_prefix(&self.path).unwrap();
+
+            let depth = rel_path.components().count();
+            let max_depth = self.max_depth.unwrap_or(usize::MAX);
+
+            if !expanded
+                && is_dir
+                && !other_device
+                && depth < max_depth
+                && (self.recurse_if_fn)(rel_path)
+            {
+                let dir = path.clone();
+                if self.order == LsOrder::PostOrder {
+                    // Visit the directory again after its entries.
+                    self.entries.push_front((entry, true));
+                    if let Err(e) = self.add_dir_entries(&dir) {
+                        return Some(Err(e));
+                    }
+                    continue;
+                }
+                // The directory itself is still returned, followed by the error.
+                if let Err(e) = self.add_dir_entries(&dir) {
+                    self.pending_err = Some(e);
+                }
+            }
+            let matches_kind = match self.filter {
+                LsFilter::All => true,
+                LsFilter::Files => is_file,
+                LsFilter::Dirs => is_dir,
+            };
+            let matches_glob = self
+                .glob
+                .as_ref()
+                .is_none_or(|glob| glob.is_match(rel_path.as_str()));
+            if matches_kind
+                && (self.min_depth..=max_depth).contains(&depth)
+                && matches_glob
+                && self.matches_regex(rel_path)
+                && self.matches_mtime(&entry)
+            {
+                if self.relative_paths {
+                    entry.path = entry.path.strip_prefix(&self.path).unwrap().to_path_buf();
+                }
+                return Some(Ok(entry));
+            }
+            if let Some(e) = self.pending_err.take() {
+                return Some(Err(e));
+            }
+        }
+        self.pending_err.take().map(Err)
+    }
+}
+
+impl<P: Fn(&Utf8Path) -> bool + ?Sized> Iterator for Ls<P> {
+    type Item = Utf8PathBuf;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.next_entry()? {
+                Ok(entry) => return Some(entry.path),
+                Err(_) => continue,
             }
-            return Ok(Some(path));
         }
-        Ok(None)
     }
 }
 
-impl Iterator for TryLsIter {
+pub struct TryLsIter<P: ?Sized = dyn Fn(&Utf8Path) -> bool + Send> {
+    ls: Ls<P>,
+}
+
+impl<P: Fn(&Utf8Path) -> bool + ?Sized> Iterator for TryLsIter<P> {
     type Item = io::Result<Utf8PathBuf>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.try_next_unfiltered() {
-            Ok(Some(path)) => match self.ls.filter {
-                LsFilter::All => Some(Ok(path)),
-                LsFilter::Files if path.is_file() => Some(Ok(path)),
-                LsFilter::Dirs if path.is_dir() => Some(Ok(path)),
-                _ => None,
-            },
-            Ok(None) => None,
-            Err(e) => Some(Err(e)),
+        self.ls
+            .next_entry()
+            .map(|entry| entry.map(LsEntry::into_path))
+    }
+}
+
+#[cfg(windows)]
+fn is_hidden(entry: &LsEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    entry.metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0
+}
+
+#[cfg(not(windows))]
+fn is_hidden(entry: &LsEntry) -> bool {
+    entry.file_name().starts_with('.')
+}
+
+/// Read the entries of the directory. Entries that can't be read are skipped and
+/// the first such error is returned alongside the others.
+fn read_dir_entries(dir: &Utf8Path, skip_hidden: bool) -> DirListing {
+    count(Counter::ReadDirCalls, 1);
+    let read_dir = match dir.read_dir_utf8() {
+        Ok(read_dir) => read_dir,
+        Err(e) => return (Vec::new(), Some(e)),
+    };
+    let mut entries = Vec::new();
+    let mut first_err = None;
+    for entry in read_dir {
+        match entry.and_then(|entry| Ok((entry.metadata()?, entry))) {
+            Ok((metadata, entry)) => {
+                let entry = LsEntry::new(entry.into_path(), metadata);
+                if !(skip_hidden && is_hidden(&entry)) {
+                    entries.push(entry);
+                }
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
         }
     }
+    (entries, first_err)
+}
+
+/// Like [`read_dir_entries`], with `tokio::fs`.
+#[cfg(feature = "tokio")]
+async fn read_dir_entries_async(dir: &Utf8Path, skip_hidden: bool) -> DirListing {
+    count(Counter::ReadDirCalls, 1);
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(e) => return (Vec::new(), Some(e)),
+    };
+    let mut entries = Vec::new();
+    let mut first_err = None;
+    loop {
+        let entry = match read_dir.next_entry().await {
+            Ok(Some(entry)) => entry,
+            Ok(None) => break,
+            Err(e) => {
+                first_err.get_or_insert(e);
+                break;
+            }
+        };
+        let path = match Utf8PathBuf::try_from(entry.path()) {
+            Ok(path) => path,
+            Err(e) => {
+                first_err.get_or_insert(io::Error::new(io::ErrorKind::InvalidData, e));
+                continue;
+            }
+        };
+        match entry.metadata().await {
+            Ok(metadata) => {
+                let entry = LsEntry::new(path, metadata);
+                if !(skip_hidden && is_hidden(&entry)) {
+                    entries.push(entry);
+                }
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    (entries, first_err)
+}
+
+/// The regular files below the directory, without following symlinks, so that
+/// nothing outside of it is visited and symlink loops end.
+pub(crate) fn files_no_follow(dir: &Utf8Path) -> io::Result<Vec<LsEntry>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in ls_map(&dir)?.into_values() {
+            if entry.is_dir() {
+                dirs.push(entry.into_path());
+            } else if entry.is_file() {
+                files.push(entry);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The entries of the directory keyed by file name.
+pub(crate) fn ls_map(dir: &Utf8Path) -> io::Result<BTreeMap<String, LsEntry>> {
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read directory {dir} due to: {e}"),
+        )
+    };
+    let mut map = BTreeMap::new();
+    for entry in dir.read_dir_utf8().map_err(err)? {
+        let entry = entry.map_err(err)?;
+        let metadata = entry.metadata().map_err(err)?;
+        let name = entry.file_name().to_string();
+        map.insert(name, LsEntry::new(entry.into_path(), metadata));
+    }
+    Ok(map)
 }
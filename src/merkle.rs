@@ -0,0 +1,175 @@
+use std::{collections::BTreeMap, fs::Metadata, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    digest::{file_digest_with, Hasher},
+    ls::ls_map,
+    Digest, FileKind, HashAlgorithm,
+};
+
+/// A node in the Merkle tree of a directory, see [`Utf8PathExt::merkle_tree`](crate::Utf8PathExt::merkle_tree).
+///
+/// The digest of a file is the digest of its content and the digest of a directory
+/// is composed from the names, kinds and digests of its children. So two trees with
+/// the same root digest are equal, and when they are not, only the subtrees with
+/// different digests need to be compared.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new()
+///     .with_file("a/one.txt", "1")
+///     .with_file("b/two.txt", "2");
+/// let before = dir.path().merkle_tree(HashAlgorithm::Sha256).unwrap();
+/// dir.join("b/two.txt").write("changed").unwrap();
+/// let after = dir.path().merkle_tree(HashAlgorithm::Sha256).unwrap();
+///
+/// assert_ne!(before.digest(), after.digest());
+/// assert_eq!(before.get("a").unwrap().digest(), after.get("a").unwrap().digest());
+/// assert_eq!(before.diff(&after), [Utf8PathBuf::from("b/two.txt")]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleNode {
+    digest: Digest,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NodeKind {
+    File,
+    Symlink,
+    /// A socket, FIFO or device, which is hashed by its kind without opening it.
+    Special(FileKind),
+    Dir(BTreeMap<String, MerkleNode>),
+}
+
+impl MerkleNode {
+    pub fn digest(&self) -> Digest {
+        self.digest
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self.kind, NodeKind::Dir(_))
+    }
+
+    /// Whether the node is a symlink, which is hashed by its target and not followed.
+    pub fn is_symlink(&self) -> bool {
+        matches!(self.kind, NodeKind::Symlink)
+    }
+
+    /// The children of a directory by name, sorted. Empty for files, symlinks and
+    /// special files.
+    pub fn children(&self) -> impl Iterator<Item = (&str, &MerkleNode)> {
+        let children = match &self.kind {
+            NodeKind::Dir(children) => Some(children),
+            _ => None,
+        };
+        children
+            .into_iter()
+            .flatten()
+            .map(|(name, node)| (name.as_str(), node))
+    }
+
+    /// The node at the relative path, e.g. `src/lib.rs`.
+    pub fn get<P: AsRef<Utf8Path>>(&self, rel: P) -> Option<&MerkleNode> {
+        let mut node = self;
+        for name in rel.as_ref().iter() {
+            let NodeKind::Dir(children) = &node.kind else {
+                return None;
+            };
+            node = children.get(name)?;
+        }
+        Some(node)
+    }
+
+    /// The relative paths of the entries that were added, removed or changed in `other`,
+    /// only descending into directories whose digests differ. A directory that only
+    /// exists on one side is reported as a single path.
+    pub fn diff(&self, other: &MerkleNode) -> Vec<Utf8PathBuf> {
+        let mut paths = Vec::new();
+        diff_into(self, other, Utf8Path::new(""), &mut paths);
+        paths
+    }
+}
+
+fn diff_into(old: &MerkleNode, new: &MerkleNode, rel: &Utf8Path, paths: &mut Vec<Utf8PathBuf>) {
+    if old.digest == new.digest {
+        return;
+    }
+    let (NodeKind::Dir(old_children), NodeKind::Dir(new_children)) = (&old.kind, &new.kind) else {
+        paths.push(rel.to_path_buf());
+        return;
+    };
+    let mut names: Vec<&String> = old_children.keys().chain(new_children.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let child_rel = rel.join(name);
+        match (old_children.get(name), new_children.get(name)) {
+            (Some(old), Some(new)) => diff_into(old, new, &child_rel, paths),
+            _ => paths.push(child_rel),
+        }
+    }
+}
+
+pub(crate) fn merkle_tree(path: &Utf8Path, algo: HashAlgorithm) -> io::Result<MerkleNode> {
+    let md = path.symlink_metadata().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read metadata of {path} due to: {e}"),
+        )
+    })?;
+    node(path, &md, algo)
+}
+
+fn node(path: &Utf8Path, md: &Metadata, algo: HashAlgorithm) -> io::Result<MerkleNode> {
+    if md.is_symlink() {
+        let target = path.read_link_utf8().map_err(|e| {
+            io::Error::new(e.kind(), format!("Could not read link {path} due to: {e}"))
+        })?;
+        let mut hasher = Hasher::new(algo);
+        hasher.update(target.as_str().as_bytes());
+        return Ok(MerkleNode {
+            digest: hasher.finalize(),
+            kind: NodeKind::Symlink,
+        });
+    }
+    let kind = FileKind::from(md.file_type());
+    if kind.is_special() {
+        let mut hasher = Hasher::new(algo);
+        hasher.update(kind.to_string().as_bytes());
+        return Ok(MerkleNode {
+            digest: hasher.finalize(),
+            kind: NodeKind::Special(kind),
+        });
+    }
+    if !md.is_dir() {
+        return Ok(MerkleNode {
+            digest: file_digest_with(path, algo)?,
+            kind: NodeKind::File,
+        });
+    }
+
+    let mut children = BTreeMap::new();
+    for (name, entry) in ls_map(path)? {
+        children.insert(name, node(entry.path(), entry.metadata(), algo)?);
+    }
+    let mut hasher = Hasher::new(algo);
+    for (name, child) in &children {
+        let kind = match child.kind {
+            NodeKind::File => b'f',
+            NodeKind::Symlink => b'l',
+            NodeKind::Special(_) => b's',
+            NodeKind::Dir(_) => b'd',
+        };
+        hasher.update(&[kind]);
+        hasher.update(&(name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update(child.digest.as_bytes());
+    }
+    Ok(MerkleNode {
+        digest: hasher.finalize(),
+        kind: NodeKind::Dir(children),
+    })
+}
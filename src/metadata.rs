@@ -0,0 +1,110 @@
+use std::{fs::Metadata, io, ops::Deref, time::SystemTime};
+
+use camino::Utf8Path;
+
+use crate::fs::fs_metadata;
+
+/// [`Metadata`] extended with fields that are only available on some platforms,
+/// see [`Utf8PathExt::extended_metadata`](crate::Utf8PathExt::extended_metadata).
+///
+/// It dereferences to the standard [`Metadata`].
+#[derive(Debug, Clone)]
+pub struct Utf8Metadata {
+    inner: Metadata,
+    birth_time: Option<SystemTime>,
+    mount_id: Option<u64>,
+    immutable: Option<bool>,
+    append_only: Option<bool>,
+}
+
+impl Utf8Metadata {
+    /// When the file was created, if the platform and file system record it.
+    pub fn birth_time(&self) -> Option<SystemTime> {
+        self.birth_time
+    }
+
+    /// The id of the mount the file is on. Only available on Linux 5.8 and later.
+    pub fn mount_id(&self) -> Option<u64> {
+        self.mount_id
+    }
+
+    /// Whether the file has the immutable attribute, so it can't be changed or
+    /// removed, not even by root. Only available on Linux.
+    pub fn is_immutable(&self) -> Option<bool> {
+        self.immutable
+    }
+
+    /// Whether the file has the append-only attribute. Only available on Linux.
+    pub fn is_append_only(&self) -> Option<bool> {
+        self.append_only
+    }
+
+    pub fn into_inner(self) -> Metadata {
+        self.inner
+    }
+}
+
+impl Deref for Utf8Metadata {
+    type Target = Metadata;
+
+    fn deref(&self) -> &Metadata {
+        &self.inner
+    }
+}
+
+impl From<Metadata> for Utf8Metadata {
+    fn from(inner: Metadata) -> Self {
+        Self {
+            birth_time: inner.created().ok(),
+            inner,
+            mount_id: None,
+            immutable: None,
+            append_only: None,
+        }
+    }
+}
+
+pub(crate) fn extended_metadata(path: &Utf8Path) -> io::Result<Utf8Metadata> {
+    #[cfg_attr(
+        not(all(target_os = "linux", any(target_env = "gnu", target_env = "musl"))),
+        allow(unused_mut)
+    )]
+    let mut md = Utf8Metadata::from(fs_metadata(path)?);
+    #[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+    if let Ok(stx) = statx(path) {
+        if stx.stx_mask & libc::STATX_MNT_ID != 0 {
+            md.mount_id = Some(stx.stx_mnt_id);
+        }
+        let supported = |attr: libc::c_int| stx.stx_attributes_mask & attr as u64 != 0;
+        let set = |attr: libc::c_int| stx.stx_attributes & attr as u64 != 0;
+        if supported(libc::STATX_ATTR_IMMUTABLE) {
+            md.immutable = Some(set(libc::STATX_ATTR_IMMUTABLE));
+        }
+        if supported(libc::STATX_ATTR_APPEND) {
+            md.append_only = Some(set(libc::STATX_ATTR_APPEND));
+        }
+    }
+    Ok(md)
+}
+
+#[cfg(all(target_os = "linux", any(target_env = "gnu", target_env = "musl")))]
+fn statx(path: &Utf8Path) -> io::Result<libc::statx> {
+    use std::{ffi::CString, mem::MaybeUninit};
+
+    let c_path = CString::new(path.as_str()).map_err(io::Error::other)?;
+    let mut stx = MaybeUninit::<libc::statx>::uninit();
+    // SAFETY: `c_path` is a valid nul terminated string and `stx` is only read on success.
+    let ret = unsafe {
+        libc::statx(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            libc::AT_STATX_SYNC_AS_STAT,
+            libc::STATX_BASIC_STATS | libc::STATX_BTIME | libc::STATX_MNT_ID,
+            stx.as_mut_ptr(),
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { stx.assume_init() })
+}
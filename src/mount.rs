@@ -0,0 +1,119 @@
+use std::{fs::Metadata, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, metadata::extended_metadata, Utf8PathExt};
+
+pub(crate) fn is_mount_point(path: &Utf8Path) -> io::Result<bool> {
+    let path = canonical(path)?;
+    is_canonical_mount_point(&path)
+}
+
+pub(crate) fn mount_point(path: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    let path = canonical(path)?;
+    let mut dir = path.as_path();
+    // The root has no parent and is always a mount point.
+    while let Some(parent) = dir.parent() {
+        if is_canonical_mount_point(dir)? {
+            break;
+        }
+        dir = parent;
+    }
+    Ok(dir.to_path_buf())
+}
+
+fn canonical(path: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(fs_canonicalize(path)?)
+        .map_err(|p| io::Error::other(format!("Path {p:?} is not valid UTF-8")))
+}
+
+#[cfg(unix)]
+fn is_canonical_mount_point(path: &Utf8Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let Some(parent) = path.parent() else {
+        return Ok(true);
+    };
+    let md = extended_metadata(path)?;
+    let parent_md = extended_metadata(parent)?;
+    // The mount id also detects bind mounts within the same file system.
+    Ok(match (md.mount_id(), parent_md.mount_id()) {
+        (Some(id), Some(parent_id)) => id != parent_id,
+        _ => md.dev() != parent_md.dev(),
+    })
+}
+
+/// Only the roots of volumes are detected, not volumes mounted in folders.
+#[cfg(not(unix))]
+fn is_canonical_mount_point(path: &Utf8Path) -> io::Result<bool> {
+    extended_metadata(path)?;
+    Ok(path.parent().is_none())
+}
+
+/// The id of the device the path is on, without following symlinks. Always `None`
+/// on other platforms than Unix.
+pub(crate) fn device_id(path: &Utf8Path) -> Option<u64> {
+    path.symlink_metadata()
+        .ok()
+        .and_then(|md| metadata_device_id(&md))
+}
+
+/// Like [`device_id`], for metadata that was already read.
+pub(crate) fn metadata_device_id(md: &Metadata) -> Option<u64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        Some(md.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = md;
+        None
+    }
+}
+
+/// Remove the path recursively like `rm -r --one-file-system`: directories on other
+/// file systems are left in place and reported as an error after removing the rest.
+pub(crate) fn rm_same_filesystem(path: &Utf8Path) -> io::Result<()> {
+    let Ok(md) = path.symlink_metadata() else {
+        return Ok(());
+    };
+    if !md.is_dir() {
+        return path.rm();
+    }
+    let mut skipped = None;
+    remove_dir_on_device(path, device_id(path), &mut skipped)?;
+    match skipped {
+        Some(skipped) => Err(io::Error::new(
+            io::ErrorKind::DirectoryNotEmpty,
+            format!("Could not remove {path} completely as {skipped} is on another file system"),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Returns false if something in the directory was skipped.
+fn remove_dir_on_device(
+    dir: &Utf8Path,
+    dev: Option<u64>,
+    skipped: &mut Option<Utf8PathBuf>,
+) -> io::Result<bool> {
+    let mut complete = true;
+    for entry in dir.ls().try_iter() {
+        let entry = entry?;
+        let is_dir = entry.symlink_metadata().is_ok_and(|md| md.is_dir());
+        if !is_dir {
+            entry.rm()?;
+        } else if device_id(&entry) != dev {
+            skipped.get_or_insert(entry);
+            complete = false;
+        } else {
+            complete &= remove_dir_on_device(&entry, dev, skipped)?;
+        }
+    }
+    if complete {
+        fs_remove_dir(dir)?;
+    }
+    Ok(complete)
+}
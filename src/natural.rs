@@ -0,0 +1,45 @@
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+/// Compare strings so that runs of digits are ordered by their numeric value,
+/// e.g. `file2` before `file10`. Text is compared case-insensitively first.
+pub(crate) fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+    loop {
+        match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(ca), Some(cb)) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let na = take_digits(&mut a_chars);
+                let nb = take_digits(&mut b_chars);
+                let ord = cmp_numbers(&na, &nb);
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+            }
+            (Some(&ca), Some(&cb)) => {
+                let ord = ca.to_lowercase().cmp(cb.to_lowercase());
+                if ord != Ordering::Equal {
+                    return ord;
+                }
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    digits
+}
+
+fn cmp_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
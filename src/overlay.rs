@@ -0,0 +1,155 @@
+use std::{collections::BTreeSet, io};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, SearchPaths, Utf8PathExt};
+
+/// A read-only view of several directories layered on top of each other, where a
+/// file in an upper layer shadows the same path in the layers below, e.g. for mods
+/// overriding the files of a game or for configuration layering.
+///
+/// The layers are looked up like [`SearchPaths`], from the top to the bottom, and
+/// paths are relative to the layer roots. A file in an upper layer also hides a
+/// directory with the same path below it, while directories in several layers are
+/// merged.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let base = TestDir::new()
+///     .with_file("conf/app.toml", "base")
+///     .with_file("conf/log.toml", "base");
+/// let local = TestDir::new().with_file("conf/app.toml", "local");
+///
+/// let fs = OverlayFs::from_iter([local.path(), base.path()]);
+/// assert_eq!(fs.read_string("conf/app.toml").unwrap(), "local");
+/// assert_eq!(fs.read_string("conf/log.toml").unwrap(), "base");
+/// assert_eq!(fs.ls("conf").unwrap(), ["conf/app.toml", "conf/log.toml"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OverlayFs {
+    layers: SearchPaths,
+}
+
+impl OverlayFs {
+    /// A view of the roots of the search paths, where the first root is the top layer.
+    pub fn new(layers: SearchPaths) -> Self {
+        Self { layers }
+    }
+
+    /// The layer roots, from the top to the bottom.
+    pub fn layers(&self) -> &[Utf8PathBuf] {
+        self.layers.roots()
+    }
+
+    /// The real path that the relative path resolves to, from the topmost layer
+    /// that has it, or `None` if it is in none of the layers.
+    pub fn resolve<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Option<Utf8PathBuf>> {
+        let path = checked_rel(path.as_ref())?;
+        for root in self.layers() {
+            let real = root.join(path);
+            if real.exists() {
+                return Ok(Some(real));
+            }
+            if hides_below(root, path) {
+                break;
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn exists<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        self.resolve(path).is_ok_and(|real| real.is_some())
+    }
+
+    pub fn is_dir<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        self.resolve(path)
+            .is_ok_and(|real| real.is_some_and(|real| real.is_dir()))
+    }
+
+    /// Read the file from the topmost layer that has it.
+    pub fn read<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        fs_read(&self.resolve_existing(path.as_ref())?)
+    }
+
+    /// Read the file from the topmost layer that has it as a string.
+    pub fn read_string<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<String> {
+        fs_read_to_string(&self.resolve_existing(path.as_ref())?)
+    }
+
+    /// The merged entries of the directory in all layers, as sorted paths relative to
+    /// the layer roots. Use an empty path for the root.
+    pub fn ls<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Vec<Utf8PathBuf>> {
+        let dir = checked_rel(dir.as_ref())?;
+        let mut names = BTreeSet::new();
+        let mut found = false;
+        for root in self.layers() {
+            let real = root.join(dir);
+            if !real.exists() {
+                if hides_below(root, dir) {
+                    break;
+                }
+                continue;
+            }
+            // A file hides everything below it.
+            if !real.is_dir() {
+                break;
+            }
+            found = true;
+            for entry in real.ls().try_iter() {
+                let entry = entry?;
+                names.insert(entry.file_name().unwrap_or_default().to_string());
+            }
+        }
+        if !found {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not list {dir} as it is not a directory in any of the layers"),
+            ));
+        }
+        Ok(names.into_iter().map(|name| dir.join(name)).collect())
+    }
+
+    fn resolve_existing(&self, path: &Utf8Path) -> io::Result<Utf8PathBuf> {
+        self.resolve(path)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not read {path} as it is in none of the layers"),
+            )
+        })
+    }
+}
+
+impl<P: Into<Utf8PathBuf>> FromIterator<P> for OverlayFs {
+    /// The layers from the top to the bottom.
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        Self::new(SearchPaths::from_iter(iter))
+    }
+}
+
+/// Whether the layer has a file in place of one of the parent directories of the
+/// path, which hides the path in the layers below.
+fn hides_below(root: &Utf8Path, path: &Utf8Path) -> bool {
+    path.ancestors()
+        .skip(1)
+        .filter(|parent| !parent.as_str().is_empty())
+        .any(|parent| {
+            let real = root.join(parent);
+            real.exists() && !real.is_dir()
+        })
+}
+
+/// The path if it stays within the layer roots.
+pub(crate) fn checked_rel(path: &Utf8Path) -> io::Result<&Utf8Path> {
+    if path
+        .components()
+        .all(|c| matches!(c, Utf8Component::Normal(_) | Utf8Component::CurDir))
+    {
+        Ok(path)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Could not use {path} as it isn't a relative path within the layers"),
+        ))
+    }
+}
@@ -0,0 +1,145 @@
+use std::io;
+
+use camino::Utf8Path;
+
+use crate::fs::{fs_metadata, fs_set_permissions};
+#[cfg(unix)]
+use crate::{
+    fs::{fs_chown, fs_lchown},
+    ls::ls_map,
+};
+#[cfg(unix)]
+use std::fs::FileType;
+
+/// The permissions of a file or directory, see [`Utf8PathExt::perms`](crate::Utf8PathExt::perms).
+///
+/// A small cross-platform view of [`std::fs::Permissions`], which it converts to and from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Permissions {
+    inner: std::fs::Permissions,
+}
+
+impl Permissions {
+    /// Whether the file can't be written. On Unix this is when no one has write
+    /// permission, like [`std::fs::Permissions::readonly`].
+    pub fn is_readonly(&self) -> bool {
+        self.inner.readonly()
+    }
+
+    /// The permission bits, including the setuid, setgid and sticky bits, e.g. `0o755`.
+    #[cfg(unix)]
+    pub fn mode(&self) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+
+        self.inner.mode() & 0o7777
+    }
+
+    pub fn into_inner(self) -> std::fs::Permissions {
+        self.inner
+    }
+}
+
+impl From<std::fs::Permissions> for Permissions {
+    fn from(inner: std::fs::Permissions) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<Permissions> for std::fs::Permissions {
+    fn from(perms: Permissions) -> Self {
+        perms.inner
+    }
+}
+
+pub(crate) fn perms(path: &Utf8Path) -> io::Result<Permissions> {
+    Ok(fs_metadata(path)?.permissions().into())
+}
+
+#[cfg(unix)]
+pub(crate) fn set_mode(path: &Utf8Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs_set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
+
+pub(crate) fn set_readonly(path: &Utf8Path, readonly: bool) -> io::Result<()> {
+    let mut perms = fs_metadata(path)?.permissions();
+    // The std `set_readonly(false)` makes the file writable for everyone on Unix.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = perms.mode();
+        perms.set_mode(if readonly {
+            mode & !0o222
+        } else {
+            mode | 0o200
+        });
+    }
+    #[cfg(not(unix))]
+    perms.set_readonly(readonly);
+    fs_set_permissions(path, perms)
+}
+
+/// Set the modes through the tree, setting a directory's mode before listing it so
+/// that a mode that grants access to it takes effect. Symlinks are left alone.
+#[cfg(unix)]
+pub(crate) fn chmod_recursive(path: &Utf8Path, file_mode: u32, dir_mode: u32) -> io::Result<()> {
+    walk_tree(path, |path, file_type| {
+        if file_type.is_symlink() {
+            Ok(())
+        } else if file_type.is_dir() {
+            set_mode(path, dir_mode)
+        } else {
+            set_mode(path, file_mode)
+        }
+    })
+}
+
+#[cfg(unix)]
+pub(crate) fn set_owner(path: &Utf8Path, uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    fs_chown(path, uid, gid)
+}
+
+/// Set the owner through the tree, changing symlinks themselves rather than their
+/// targets, like `chown -R`.
+#[cfg(unix)]
+pub(crate) fn set_owner_recursive(
+    path: &Utf8Path,
+    uid: Option<u32>,
+    gid: Option<u32>,
+) -> io::Result<()> {
+    walk_tree(path, |path, file_type| {
+        if file_type.is_symlink() {
+            fs_lchown(path, uid, gid)
+        } else {
+            fs_chown(path, uid, gid)
+        }
+    })
+}
+
+/// Call `f` for the path and everything below it without following symlinks,
+/// calling it for a directory before listing it.
+#[cfg(unix)]
+fn walk_tree<F>(path: &Utf8Path, mut f: F) -> io::Result<()>
+where
+    F: FnMut(&Utf8Path, FileType) -> io::Result<()>,
+{
+    let md = path
+        .symlink_metadata()
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not read {path} due to: {e}")))?;
+    f(path, md.file_type())?;
+    if !md.is_dir() {
+        return Ok(());
+    }
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in ls_map(&dir)?.into_values() {
+            f(entry.path(), entry.file_type())?;
+            if entry.is_dir() {
+                dirs.push(entry.into_path());
+            }
+        }
+    }
+    Ok(())
+}
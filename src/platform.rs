@@ -0,0 +1,171 @@
+use std::{error::Error, fmt, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+/// An operating system with its own rules for what makes a valid path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// Linux, also used for the other Unix-like systems.
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    /// The platform this program runs on. Unix-like systems other than macOS are
+    /// treated as [`Platform::Linux`].
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        }
+    }
+
+    /// The maximum length of a single file or directory name, in the unit of
+    /// [`Platform::len_unit`].
+    pub fn max_component_len(self) -> usize {
+        255
+    }
+
+    /// The maximum length of a whole path, in the unit of [`Platform::len_unit`].
+    ///
+    /// On Windows this is the classic `MAX_PATH` limit that most programs are
+    /// still bound by. Paths starting with `\\?\` may be up to 32767 long.
+    pub fn max_path_len(self) -> usize {
+        match self {
+            Platform::Linux => 4095,
+            Platform::MacOs => 1023,
+            Platform::Windows => 259,
+        }
+    }
+
+    /// The unit lengths are measured in: `"bytes"`, or `"UTF-16 units"` on Windows.
+    pub fn len_unit(self) -> &'static str {
+        match self {
+            Platform::Windows => "UTF-16 units",
+            Platform::Linux | Platform::MacOs => "bytes",
+        }
+    }
+
+    pub(crate) fn len_of(self, s: &str) -> usize {
+        match self {
+            Platform::Windows => s.encode_utf16().count(),
+            Platform::Linux | Platform::MacOs => s.len(),
+        }
+    }
+
+    pub(crate) fn is_separator(self, c: char) -> bool {
+        c == '/' || (self == Platform::Windows && c == '\\')
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Platform::Linux => "Linux",
+            Platform::MacOs => "macOS",
+            Platform::Windows => "Windows",
+        })
+    }
+}
+
+/// The error inside the `io::Error` returned by [`Utf8PathExt::validate_length`](crate::Utf8PathExt::validate_length)
+/// when the path or one of its components is too long.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let name = "a".repeat(300);
+/// let err = Utf8Path::new(&name).validate_length_for(Platform::Linux).unwrap_err();
+/// let too_long = err.get_ref().and_then(|e| e.downcast_ref::<PathTooLong>()).unwrap();
+/// assert_eq!(too_long.component(), Some(name.as_str()));
+/// assert_eq!((too_long.length(), too_long.max_length()), (300, 255));
+/// ```
+#[derive(Debug)]
+pub struct PathTooLong {
+    path: Utf8PathBuf,
+    component: Option<String>,
+    len: usize,
+    max: usize,
+    platform: Platform,
+}
+
+impl PathTooLong {
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// The component that is too long, or `None` if the whole path is too long.
+    pub fn component(&self) -> Option<&str> {
+        self.component.as_deref()
+    }
+
+    /// The length in the unit of [`Platform::len_unit`].
+    pub fn length(&self) -> usize {
+        self.len
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max
+    }
+
+    pub fn platform(&self) -> Platform {
+        self.platform
+    }
+}
+
+impl fmt::Display for PathTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let unit = self.platform.len_unit();
+        match &self.component {
+            Some(component) => write!(
+                f,
+                "The name {component} in {} is {} {unit} long, but at most {} are allowed on {}",
+                self.path, self.len, self.max, self.platform
+            ),
+            None => write!(
+                f,
+                "The path {} is {} {unit} long, but at most {} are allowed on {}",
+                self.path, self.len, self.max, self.platform
+            ),
+        }
+    }
+}
+
+impl Error for PathTooLong {}
+
+pub(crate) fn validate_length(path: &Utf8Path, platform: Platform) -> io::Result<()> {
+    let too_long = |component: Option<&str>, len, max| {
+        io::Error::new(
+            io::ErrorKind::InvalidFilename,
+            PathTooLong {
+                path: path.to_path_buf(),
+                component: component.map(str::to_string),
+                len,
+                max,
+                platform,
+            },
+        )
+    };
+
+    let max = match platform {
+        Platform::Windows if path.as_str().starts_with(r"\\?\") => 32767,
+        _ => platform.max_path_len(),
+    };
+    let len = platform.len_of(path.as_str());
+    if len > max {
+        return Err(too_long(None, len, max));
+    }
+
+    let max = platform.max_component_len();
+    for component in path.as_str().split(|c| platform.is_separator(c)) {
+        let len = platform.len_of(component);
+        if len > max {
+            return Err(too_long(Some(component), len, max));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+/// What to do when a file that is about to be written already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replace the existing file.
+    #[default]
+    Overwrite,
+    /// Keep the existing file and continue.
+    Skip,
+    /// Fail with an `AlreadyExists` error.
+    Error,
+    /// Replace the existing file only if the source was modified more recently.
+    /// Sources without a modification time, like embedded files, always replace it.
+    IfNewer,
+}
+
+/// What to do when a directory is copied to a directory that already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExistingDirPolicy {
+    /// Copy the contents into the existing directory. Files that exist in both are
+    /// replaced and files that only exist in the destination are kept.
+    #[default]
+    Merge,
+    /// Remove the existing directory first, so the result is an exact copy of the source.
+    Replace,
+    /// Fail with an `AlreadyExists` error.
+    Error,
+}
+
+/// What to do when copying a socket, FIFO or device, which can't be copied by
+/// copying its bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecialFilePolicy {
+    /// Fail with an `InvalidInput` error.
+    #[default]
+    Error,
+    /// Leave it out of the copy and continue.
+    Skip,
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, fmt, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{escape::is_reserved_on_windows, Platform, Utf8PathExt};
+
+/// A file system to check a tree against with [`Utf8PathExt::check_tree_portability`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TargetFs {
+    /// FAT32, common on older USB sticks and SD cards. Files are limited to 4 GiB.
+    Fat32,
+    /// exFAT, common on newer USB sticks and SD cards.
+    ExFat,
+    /// NTFS, the default on Windows. Symlinks are supported, but only created with
+    /// special privileges.
+    Ntfs,
+}
+
+impl TargetFs {
+    /// The largest file size supported, if there is a limit.
+    pub fn max_file_size(self) -> Option<u64> {
+        match self {
+            TargetFs::Fat32 => Some(u32::MAX as u64),
+            TargetFs::ExFat | TargetFs::Ntfs => None,
+        }
+    }
+
+    pub fn supports_symlinks(self) -> bool {
+        self == TargetFs::Ntfs
+    }
+}
+
+impl fmt::Display for TargetFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TargetFs::Fat32 => "FAT32",
+            TargetFs::ExFat => "exFAT",
+            TargetFs::Ntfs => "NTFS",
+        })
+    }
+}
+
+/// An entry that can't be copied as is to the target file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortabilityIssue {
+    /// The path relative to the checked directory.
+    pub path: Utf8PathBuf,
+    pub problem: PortabilityProblem,
+}
+
+/// Why an entry can't be copied to the target file system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortabilityProblem {
+    /// The name contains a character that isn't allowed, like `:` or `?`.
+    InvalidChar(char),
+    /// The name is reserved for a device, like `CON` or `nul.txt`.
+    ReservedName,
+    /// The name ends with a dot or space, which would be stripped.
+    TrailingDotOrSpace,
+    /// The name is longer than 255 UTF-16 units.
+    NameTooLong,
+    /// The file is larger than the file system supports.
+    FileTooLarge { len: u64, max: u64 },
+    /// The file system doesn't support symlinks.
+    Symlink,
+    /// The name only differs in case from another entry in the same directory, which
+    /// is the same name on a case insensitive file system.
+    CaseConflict(Utf8PathBuf),
+}
+
+impl fmt::Display for PortabilityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidChar(c) => write!(f, "the name contains the invalid character {c:?}"),
+            Self::ReservedName => write!(f, "the name is reserved"),
+            Self::TrailingDotOrSpace => write!(f, "the name ends with a dot or space"),
+            Self::NameTooLong => write!(f, "the name is too long"),
+            Self::FileTooLarge { len, max } => {
+                write!(
+                    f,
+                    "the file is {len} bytes, but at most {max} are supported"
+                )
+            }
+            Self::Symlink => write!(f, "symlinks are not supported"),
+            Self::CaseConflict(other) => write!(f, "the name only differs in case from {other}"),
+        }
+    }
+}
+
+impl fmt::Display for PortabilityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.problem)
+    }
+}
+
+pub(crate) fn check_tree_portability(
+    dir: &Utf8Path,
+    target: TargetFs,
+) -> io::Result<Vec<PortabilityIssue>> {
+    let base = dir.to_path_buf();
+    // Symlinks are reported, not followed.
+    let walk = dir
+        .ls()
+        .recurse_if(move |rel| !base.join(rel).is_symlink())
+        .sorted_natural()
+        .relative_paths();
+
+    let mut issues = Vec::new();
+    let mut seen: HashMap<String, Utf8PathBuf> = HashMap::new();
+    for rel in walk.try_iter() {
+        let rel = rel?;
+        let mut report = |problem| {
+            issues.push(PortabilityIssue {
+                path: rel.clone(),
+                problem,
+            })
+        };
+
+        if let Some(problem) = name_problem(rel.file_name().unwrap_or_default()) {
+            report(problem);
+        }
+        if let Some(other) = seen.insert(rel.as_str().to_lowercase(), rel.clone()) {
+            report(PortabilityProblem::CaseConflict(other));
+        }
+
+        let path = dir.join(&rel);
+        let md = path.symlink_metadata().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not read metadata of {path} due to: {e}"),
+            )
+        })?;
+        if md.is_symlink() && !target.supports_symlinks() {
+            report(PortabilityProblem::Symlink);
+        }
+        if let Some(max) = target
+            .max_file_size()
+            .filter(|max| md.is_file() && md.len() > *max)
+        {
+            report(PortabilityProblem::FileTooLarge { len: md.len(), max });
+        }
+    }
+    Ok(issues)
+}
+
+/// The first reason the name isn't allowed on FAT32, exFAT and NTFS, which share
+/// the Windows naming rules.
+fn name_problem(name: &str) -> Option<PortabilityProblem> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| c.is_ascii_control() || r#"<>:"|?*\"#.contains(*c))
+    {
+        Some(PortabilityProblem::InvalidChar(c))
+    } else if is_reserved_on_windows(name) {
+        Some(PortabilityProblem::ReservedName)
+    } else if name.ends_with(['.', ' ']) {
+        Some(PortabilityProblem::TrailingDotOrSpace)
+    } else if Platform::Windows.len_of(name) > Platform::Windows.max_component_len() {
+        Some(PortabilityProblem::NameTooLong)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,119 @@
+use std::{io, time::SystemTime};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+#[cfg(not(unix))]
+use crate::link;
+use crate::{
+    backup::unique_snapshot_name, fs::*, natural::natural_cmp, temp::sibling_temp_path, Utf8PathExt,
+};
+
+pub(crate) fn publish_dir(staging: &Utf8Path, live: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    staging.assert_dir()?;
+    if live.file_name().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Could not publish to {live} as it has no file name"),
+        ));
+    }
+    let root = live.parent().unwrap_or(Utf8Path::new(""));
+    if live.exists() && !live.is_symlink() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Could not publish to {live} as it is not a symlink"),
+        ));
+    }
+    root.mkdirs()?;
+    let version = root.join(unique_snapshot_name(root, SystemTime::now()));
+    staging.mv(&version)?;
+    flip(&version, live).inspect_err(|_| {
+        let _ = fs_rename(&version, staging);
+    })
+}
+
+/// Point the `live` symlink to the version by replacing it with a new symlink, which
+/// is atomic.
+#[cfg(unix)]
+fn flip(version: &Utf8Path, live: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    let temp = sibling_temp_path(live, "publish");
+    // Relative, so the versions can be moved together with the link.
+    let target = version.file_name().unwrap_or_default();
+    std::os::unix::fs::symlink(target, &temp)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not link {temp} due to: {e}")))?;
+    fs_rename(&temp, live).inspect_err(|_| {
+        let _ = temp.rm();
+    })?;
+    Ok(version.to_path_buf())
+}
+
+/// Point the `live` junction to the version by swapping in a new junction with two
+/// renames, as creating symlinks needs privileges on Windows and a directory link
+/// can't be renamed over another one.
+#[cfg(not(unix))]
+fn flip(version: &Utf8Path, live: &Utf8Path) -> io::Result<Utf8PathBuf> {
+    let temp = sibling_temp_path(live, "publish");
+    link::create_junction(&temp, version)?;
+    let previous = sibling_temp_path(live, "previous");
+    let had_previous = live.symlink_metadata().is_ok();
+    if had_previous {
+        fs_rename(live, &previous).inspect_err(|_| {
+            let _ = temp.rm();
+        })?;
+    }
+    if let Err(e) = fs_rename(&temp, live) {
+        if had_previous {
+            let _ = fs_rename(&previous, live);
+        }
+        let _ = temp.rm();
+        return Err(e);
+    }
+    // Only removes the junction, the previous version is kept. The new version is
+    // live already, so a leftover junction is no error.
+    let _ = previous.rm();
+    Ok(version.to_path_buf())
+}
+
+pub(crate) fn gc_generations(root: &Utf8Path, keep: usize) -> io::Result<Vec<Utf8PathBuf>> {
+    root.assert_dir()?;
+    let mut generations = Vec::new();
+    let mut in_use = Vec::new();
+    for entry in root.ls().entries() {
+        if entry.is_symlink() {
+            if let Ok(target) = fs_canonicalize(entry.path()) {
+                in_use.push(target);
+            }
+        } else if entry.is_dir() && is_generation_name(entry.file_name()) {
+            generations.push(entry.into_path());
+        }
+    }
+    generations.sort_by(|a, b| natural_cmp(a.as_str(), b.as_str()));
+
+    let old = generations.len().saturating_sub(keep);
+    let mut removed = Vec::new();
+    for generation in generations.into_iter().take(old) {
+        if fs_canonicalize(&generation).is_ok_and(|path| in_use.contains(&path)) {
+            continue;
+        }
+        generation.rm()?;
+        removed.push(generation);
+    }
+    Ok(removed)
+}
+
+/// Whether the name is like `2024-05-01T13-45-10Z` or `2024-05-01T13-45-10Z-2`.
+fn is_generation_name(name: &str) -> bool {
+    let (time, suffix) = name.split_at(name.len().min(20));
+    let pattern_matches = time.len() == 20
+        && time
+            .bytes()
+            .zip(b"0000-00-00T00-00-00Z")
+            .all(|(b, p)| match p {
+                b'0' => b.is_ascii_digit(),
+                _ => b == *p,
+            });
+    pattern_matches
+        && (suffix.is_empty()
+            || suffix
+                .strip_prefix('-')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())))
+}
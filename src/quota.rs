@@ -0,0 +1,184 @@
+use std::{io, sync::Mutex, time::SystemTime};
+
+use camino::{Utf8Component, Utf8Path, Utf8PathBuf};
+
+use crate::{ls::files_no_follow, ByteSize, Utf8PathExt};
+
+/// A directory that limits the total size of the files written through it.
+///
+/// The current usage is computed when the directory is opened and then tracked for
+/// every write and removal done through the `QuotaDir`. Changes made by other means
+/// are only picked up by [`QuotaDir::refresh`].
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let quota = QuotaDir::open(dir.join("quota"), 10).unwrap();
+/// quota.write("a.txt", "hello").unwrap();
+/// assert!(quota.write("b.txt", "world!").is_err());
+/// assert_eq!(quota.used(), ByteSize(5));
+/// ```
+#[derive(Debug)]
+pub struct QuotaDir {
+    root: Utf8PathBuf,
+    cap: u64,
+    evict_oldest: bool,
+    used: Mutex<u64>,
+}
+
+impl QuotaDir {
+    /// Open or create the directory with a cap in bytes.
+    pub fn open<P: Into<Utf8PathBuf>>(root: P, cap: u64) -> io::Result<Self> {
+        let root = root.into();
+        root.mkdirs()?;
+        let used = dir_usage(&root)?;
+        Ok(Self {
+            root,
+            cap,
+            evict_oldest: false,
+            used: Mutex::new(used),
+        })
+    }
+
+    /// Instead of rejecting a write that would exceed the cap, remove the least
+    /// recently modified files until it fits.
+    pub fn evict_oldest(self) -> Self {
+        Self {
+            evict_oldest: true,
+            ..self
+        }
+    }
+
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    pub fn cap(&self) -> ByteSize {
+        ByteSize(self.cap)
+    }
+
+    pub fn used(&self) -> ByteSize {
+        ByteSize(*self.used.lock().unwrap())
+    }
+
+    pub fn available(&self) -> ByteSize {
+        ByteSize(self.cap.saturating_sub(*self.used.lock().unwrap()))
+    }
+
+    /// Write a file at the path relative to the root, replacing it if it exists.
+    ///
+    /// Fails with a `StorageFull` error if the write would exceed the cap, also when
+    /// evicting the oldest files didn't free enough.
+    pub fn write<P: AsRef<Utf8Path>, B: AsRef<[u8]>>(
+        &self,
+        rel: P,
+        buf: B,
+    ) -> io::Result<Utf8PathBuf> {
+        let path = self.resolve(rel.as_ref())?;
+        let buf = buf.as_ref();
+        let mut used = self.used.lock().unwrap();
+        let existing = file_len(&path);
+        let needed = used.saturating_sub(existing) + buf.len() as u64;
+
+        if needed > self.cap {
+            if !self.evict_oldest || buf.len() as u64 > self.cap {
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    format!(
+                        "Could not write {path} as it would exceed the quota of {} ({} used)",
+                        ByteSize(self.cap),
+                        ByteSize(*used)
+                    ),
+                ));
+            }
+            let freed = self.evict(needed - self.cap, &path)?;
+            *used = used.saturating_sub(freed);
+            if freed < needed - self.cap {
+                // The tracked usage can include files that were removed by other means.
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    format!(
+                        "Could not write {path} as evicting files freed {} of the {} needed",
+                        ByteSize(freed),
+                        ByteSize(needed - self.cap)
+                    ),
+                ));
+            }
+        }
+        path.write(buf)?;
+        // The replaced file is never evicted, so its length is still part of `used`.
+        *used = used.saturating_sub(existing) + buf.len() as u64;
+        Ok(path)
+    }
+
+    /// Remove the file or directory at the path relative to the root.
+    pub fn rm<P: AsRef<Utf8Path>>(&self, rel: P) -> io::Result<()> {
+        let path = self.resolve(rel.as_ref())?;
+        let mut used = self.used.lock().unwrap();
+        let freed = if path.symlink_metadata().is_ok_and(|md| md.is_dir()) {
+            dir_usage(&path)?
+        } else {
+            file_len(&path)
+        };
+        path.rm()?;
+        *used = used.saturating_sub(freed);
+        Ok(())
+    }
+
+    /// Recompute the usage from the files on disk.
+    pub fn refresh(&self) -> io::Result<()> {
+        *self.used.lock().unwrap() = dir_usage(&self.root)?;
+        Ok(())
+    }
+
+    fn resolve(&self, rel: &Utf8Path) -> io::Result<Utf8PathBuf> {
+        if !rel
+            .components()
+            .all(|c| matches!(c, Utf8Component::Normal(_) | Utf8Component::CurDir))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Path \"{rel}\" must be relative and stay within the quota directory"),
+            ));
+        }
+        Ok(self.root.join(rel))
+    }
+
+    /// Remove the oldest files, except `keep`, until at least `bytes` are freed.
+    /// Returns the number of bytes freed.
+    fn evict(&self, bytes: u64, keep: &Utf8Path) -> io::Result<u64> {
+        let mut files: Vec<(SystemTime, u64, Utf8PathBuf)> = files_no_follow(&self.root)?
+            .into_iter()
+            .filter(|entry| entry.path() != keep)
+            .filter_map(|entry| {
+                let md = entry.metadata();
+                Some((md.modified().ok()?, md.len(), entry.into_path()))
+            })
+            .collect();
+        files.sort();
+
+        let mut freed = 0;
+        for (_, len, path) in files {
+            if freed >= bytes {
+                break;
+            }
+            path.rm()?;
+            freed += len;
+        }
+        Ok(freed)
+    }
+}
+
+/// The length of the file, or 0 for anything else, as only files are counted.
+fn file_len(path: &Utf8Path) -> u64 {
+    path.symlink_metadata()
+        .ok()
+        .filter(|md| md.is_file())
+        .map_or(0, |md| md.len())
+}
+
+fn dir_usage(dir: &Utf8Path) -> io::Result<u64> {
+    let files = files_no_follow(dir)?;
+    Ok(files.iter().map(|entry| entry.metadata().len()).sum())
+}
@@ -0,0 +1,192 @@
+use std::{collections::BTreeSet, io};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::*, overlay::checked_rel, Utf8PathExt, Utf8TempDir};
+
+/// A copy-on-write view of a directory, where writes and removals go to a temporary
+/// layer while reads fall through to the real tree, e.g. to preview a refactoring.
+///
+/// Nothing in the real tree is changed until [`ScratchOverlay::commit`], and the
+/// changes are thrown away by [`ScratchOverlay::discard`] or when dropped. Paths are
+/// relative to the root.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new()
+///     .with_file("src/main.rs", "fn main() {}")
+///     .with_file("src/old.rs", "");
+///
+/// let mut scratch = ScratchOverlay::new(dir.path()).unwrap();
+/// scratch.write("src/main.rs", "fn main() { run() }").unwrap();
+/// scratch.remove("src/old.rs").unwrap();
+/// assert_eq!(scratch.ls("src").unwrap(), ["src/main.rs"]);
+/// dir.assert_file("src/main.rs", "fn main() {}");
+///
+/// scratch.commit().unwrap();
+/// dir.assert_tree(&["src/", "src/main.rs"]);
+/// dir.assert_file("src/main.rs", "fn main() { run() }");
+/// ```
+#[derive(Debug)]
+pub struct ScratchOverlay {
+    root: Utf8PathBuf,
+    upper: Utf8TempDir,
+    /// The removed paths of the real tree, which hide everything below them.
+    removed: BTreeSet<Utf8PathBuf>,
+}
+
+impl ScratchOverlay {
+    /// Start a scratch view of the directory.
+    pub fn new<P: Into<Utf8PathBuf>>(root: P) -> io::Result<Self> {
+        let root = root.into();
+        root.assert_dir()?;
+        Ok(Self {
+            root,
+            upper: Utf8TempDir::new()?,
+            removed: BTreeSet::new(),
+        })
+    }
+
+    /// The real directory.
+    pub fn root(&self) -> &Utf8Path {
+        &self.root
+    }
+
+    /// Write the file in the scratch layer, creating its parent directories.
+    pub fn write<P: AsRef<Utf8Path>, B: AsRef<[u8]>>(&mut self, path: P, buf: B) -> io::Result<()> {
+        let path = checked_rel(path.as_ref())?;
+        if self.is_dir(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                format!("Could not write {path} as it is a directory"),
+            ));
+        }
+        if let Some(parent) = path
+            .ancestors()
+            .skip(1)
+            .find(|parent| self.exists(parent) && !self.is_dir(parent))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("Could not write {path} as {parent} is not a directory"),
+            ));
+        }
+        self.upper.join(path).write(buf)?;
+        self.removed.remove(path);
+        Ok(())
+    }
+
+    /// Remove the file or directory from the view.
+    pub fn remove<P: AsRef<Utf8Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = checked_rel(path.as_ref())?;
+        if !self.exists(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not remove {path} as it does not exist"),
+            ));
+        }
+        self.upper.join(path).rm()?;
+        if self.lower(path).is_some() {
+            self.removed.retain(|removed| !removed.starts_with(path));
+            self.removed.insert(path.to_path_buf());
+        }
+        Ok(())
+    }
+
+    pub fn exists<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        self.resolve(path.as_ref()).is_some()
+    }
+
+    pub fn is_dir<P: AsRef<Utf8Path>>(&self, path: P) -> bool {
+        self.resolve(path.as_ref())
+            .is_some_and(|real| real.is_dir())
+    }
+
+    pub fn read<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<Vec<u8>> {
+        fs_read(&self.resolve_existing(path.as_ref())?)
+    }
+
+    pub fn read_string<P: AsRef<Utf8Path>>(&self, path: P) -> io::Result<String> {
+        fs_read_to_string(&self.resolve_existing(path.as_ref())?)
+    }
+
+    /// The entries of the directory in the view, as sorted paths relative to the root.
+    /// Use an empty path for the root.
+    pub fn ls<P: AsRef<Utf8Path>>(&self, dir: P) -> io::Result<Vec<Utf8PathBuf>> {
+        let dir = checked_rel(dir.as_ref())?;
+        if !self.is_dir(dir) {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not list {dir} as it is not a directory"),
+            ));
+        }
+        let mut entries = BTreeSet::new();
+        for real in [self.upper.join(dir), self.root.join(dir)] {
+            if !real.is_dir() {
+                continue;
+            }
+            for entry in real.ls().try_iter() {
+                let entry = dir.join(entry?.file_name().unwrap_or_default());
+                if self.exists(&entry) {
+                    entries.insert(entry);
+                }
+            }
+        }
+        Ok(entries.into_iter().collect())
+    }
+
+    /// The files written in the scratch layer, as sorted paths relative to the root.
+    pub fn written(&self) -> Vec<Utf8PathBuf> {
+        let mut files: Vec<_> = self.upper.ls().recurse().files().relative_paths().collect();
+        files.sort();
+        files
+    }
+
+    /// The removed paths of the real tree, sorted.
+    pub fn removed(&self) -> impl Iterator<Item = &Utf8Path> {
+        self.removed.iter().map(|path| path.as_path())
+    }
+
+    /// Apply the removals and writes to the real tree.
+    pub fn commit(self) -> io::Result<()> {
+        for path in &self.removed {
+            self.root.join(path).rm()?;
+        }
+        self.upper.cp(&self.root)?;
+        self.upper.close()
+    }
+
+    /// Throw away the changes, which is the same as dropping the overlay but returns
+    /// the error if the scratch layer can't be removed.
+    pub fn discard(self) -> io::Result<()> {
+        self.upper.close()
+    }
+
+    /// The real path of the entry in the view.
+    fn resolve(&self, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        let path = checked_rel(path).ok()?;
+        let upper = self.upper.join(path);
+        if upper.exists() {
+            return Some(upper);
+        }
+        self.lower(path)
+    }
+
+    /// The path in the real tree, unless it was removed.
+    fn lower(&self, path: &Utf8Path) -> Option<Utf8PathBuf> {
+        if path.ancestors().any(|parent| self.removed.contains(parent)) {
+            return None;
+        }
+        Some(self.root.join(path)).filter(|real| real.exists())
+    }
+
+    fn resolve_existing(&self, path: &Utf8Path) -> io::Result<Utf8PathBuf> {
+        self.resolve(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Could not read {path} as it does not exist"),
+            )
+        })
+    }
+}
@@ -0,0 +1,69 @@
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Utf8PathExt;
+
+/// An ordered list of root directories to look up relative paths in, as used by
+/// include paths, asset overlays and plugin loaders.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let search = SearchPaths::from_iter(["overrides", "src"]);
+/// assert_eq!(search.resolve("lib.rs").unwrap(), "src/lib.rs");
+/// assert_eq!(search.resolve("missing.rs"), None);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchPaths {
+    roots: Vec<Utf8PathBuf>,
+}
+
+impl SearchPaths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a root with lower priority than the existing ones.
+    pub fn push<P: Into<Utf8PathBuf>>(&mut self, root: P) {
+        self.roots.push(root.into());
+    }
+
+    pub fn roots(&self) -> &[Utf8PathBuf] {
+        &self.roots
+    }
+
+    /// The path in the first root where `rel` exists.
+    pub fn resolve<P: AsRef<Utf8Path>>(&self, rel: P) -> Option<Utf8PathBuf> {
+        self.candidates(rel.as_ref()).find(|p| p.exists())
+    }
+
+    /// The paths in all roots where `rel` exists, in priority order.
+    pub fn resolve_all<P: AsRef<Utf8Path>>(&self, rel: P) -> Vec<Utf8PathBuf> {
+        self.candidates(rel.as_ref())
+            .filter(|p| p.exists())
+            .collect()
+    }
+
+    /// Map a path in one of the roots back to its relative path, together with
+    /// the index of the root.
+    pub fn relative<'a>(&self, path: &'a Utf8Path) -> Option<(usize, &'a Utf8Path)> {
+        path.strip_any_prefix(&self.roots)
+    }
+
+    fn candidates<'a>(&'a self, rel: &'a Utf8Path) -> impl Iterator<Item = Utf8PathBuf> + 'a {
+        self.roots.iter().map(move |root| root.join(rel))
+    }
+}
+
+impl<P: Into<Utf8PathBuf>> FromIterator<P> for SearchPaths {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        Self {
+            roots: iter.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<P: Into<Utf8PathBuf>> Extend<P> for SearchPaths {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, iter: I) {
+        self.roots.extend(iter.into_iter().map(Into::into));
+    }
+}
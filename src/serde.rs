@@ -0,0 +1,137 @@
+//! Helpers for path fields in config structs, to be used with `#[serde(with = "...")]`.
+//!
+//! All modules serialize the path unchanged.
+//!
+//! ```
+//! use camino_fs::{serde::with_base_dir, Utf8Path, Utf8PathBuf};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     #[serde(with = "camino_fs::serde::relative_to_base")]
+//!     output: Utf8PathBuf,
+//! }
+//!
+//! let config: Config = with_base_dir("/etc/app", || {
+//!     serde_json::from_str(r#"{ "output": "out/log.txt" }"#).unwrap()
+//! });
+//! assert_eq!(config.output, Utf8Path::new("/etc/app/out/log.txt"));
+//! ```
+
+use std::cell::RefCell;
+
+use ::serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::Utf8PathExt;
+
+thread_local! {
+    static BASE_DIR: RefCell<Option<Utf8PathBuf>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with `dir` as the base directory for [`relative_to_base`], typically
+/// the directory of the config file being deserialized.
+pub fn with_base_dir<P: Into<Utf8PathBuf>, R, F: FnOnce() -> R>(dir: P, f: F) -> R {
+    let prev = BASE_DIR.with(|base| base.replace(Some(dir.into())));
+    let _reset = ResetBaseDir(prev);
+    f()
+}
+
+struct ResetBaseDir(Option<Utf8PathBuf>);
+
+impl Drop for ResetBaseDir {
+    fn drop(&mut self) {
+        BASE_DIR.with(|base| *base.borrow_mut() = self.0.take());
+    }
+}
+
+fn serialize_path<S: Serializer>(path: &Utf8Path, serializer: S) -> Result<S::Ok, S::Error> {
+    path.serialize(serializer)
+}
+
+fn deserialize_with<'de, D, F>(deserializer: D, f: F) -> Result<Utf8PathBuf, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(String) -> std::io::Result<Utf8PathBuf>,
+{
+    let s = String::deserialize(deserializer)?;
+    f(s).map_err(D::Error::custom)
+}
+
+/// Expands a leading `~` to the home directory of the current user.
+pub mod expand_home {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Utf8Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_path(path, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Utf8PathBuf, D::Error> {
+        deserialize_with(deserializer, |s| Utf8Path::new(&s).expand_home())
+    }
+}
+
+/// Expands `$VAR` and `${VAR}` environment variables and a leading `~`.
+pub mod expand_env {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Utf8Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_path(path, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Utf8PathBuf, D::Error> {
+        deserialize_with(deserializer, |s| {
+            Utf8Path::new(&s).expand_env()?.expand_home()
+        })
+    }
+}
+
+/// Makes relative paths absolute by joining them with the current working directory.
+pub mod absolute {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Utf8Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_path(path, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Utf8PathBuf, D::Error> {
+        deserialize_with(deserializer, |s| {
+            let path = std::path::absolute(s)?;
+            Utf8PathBuf::from_path_buf(path)
+                .map_err(|p| std::io::Error::other(format!("Path {p:?} is not valid UTF-8")))
+        })
+    }
+}
+
+/// Resolves relative paths against the directory set with [`with_base_dir`].
+/// Fails if deserialized outside of [`with_base_dir`].
+pub mod relative_to_base {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Utf8Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_path(path, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Utf8PathBuf, D::Error> {
+        deserialize_with(deserializer, |s| {
+            let path = Utf8PathBuf::from(s);
+            if path.is_absolute() {
+                return Ok(path);
+            }
+            BASE_DIR.with(|base| match base.borrow().as_ref() {
+                Some(base) => Ok(base.join(&path)),
+                None => Err(std::io::Error::other(format!(
+                    "Could not resolve \"{path}\" as no base directory is set"
+                ))),
+            })
+        })
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::Metadata,
+    io,
+    iter::Sum,
+    ops::{Add, AddAssign},
+};
+
+use camino::Utf8Path;
+
+use crate::{
+    fs::fs_metadata,
+    ls::ls_map,
+    mount::{device_id, metadata_device_id},
+};
+
+const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// A number of bytes that is displayed in human readable binary units, e.g. `3.4 MiB`.
+///
+/// The formatting precision defaults to one decimal and can be set with `{:.2}`.
+///
+/// ```
+/// use camino_fs::ByteSize;
+///
+/// assert_eq!(ByteSize(512).to_string(), "512 B");
+/// assert_eq!(ByteSize(3_565_158).to_string(), "3.4 MiB");
+/// assert_eq!(format!("{:.2}", ByteSize(1536)), "1.50 KiB");
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        let precision = f.precision().unwrap_or(1);
+        write!(f, "{value:.precision$} {}", UNITS[unit])
+    }
+}
+
+impl From<u64> for ByteSize {
+    fn from(bytes: u64) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<ByteSize> for u64 {
+    fn from(size: ByteSize) -> Self {
+        size.0
+    }
+}
+
+impl Add for ByteSize {
+    type Output = ByteSize;
+
+    fn add(self, rhs: ByteSize) -> ByteSize {
+        ByteSize(self.0 + rhs.0)
+    }
+}
+
+impl AddAssign for ByteSize {
+    fn add_assign(&mut self, rhs: ByteSize) {
+        self.0 += rhs.0;
+    }
+}
+
+impl Sum for ByteSize {
+    fn sum<I: Iterator<Item = ByteSize>>(iter: I) -> Self {
+        ByteSize(iter.map(|s| s.0).sum())
+    }
+}
+
+/// What to count as the size of a file, see [`Utf8PathExt::size_with`](crate::Utf8PathExt::size_with).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SizeMode {
+    /// The length of the contents, like `du --apparent-size`.
+    #[default]
+    Apparent,
+    /// The space allocated on disk, which is rounded up to whole blocks and smaller
+    /// for sparse files, like `du`.
+    ///
+    /// Only supported on Unix, elsewhere this is the same as [`SizeMode::Apparent`].
+    Disk,
+}
+
+impl SizeMode {
+    fn of(self, md: &Metadata) -> u64 {
+        #[cfg(unix)]
+        if self == SizeMode::Disk {
+            use std::os::unix::fs::MetadataExt;
+
+            return md.blocks() * 512;
+        }
+        md.len()
+    }
+}
+
+/// The size of the file, or the total size of the directory including the
+/// directory entries themselves. Symlinks within directories are not followed,
+/// and files with several hard links in the tree are counted once.
+///
+/// With `same_filesystem_only`, entries on another file system are left out
+/// entirely, including the mount points, like `du -x`.
+pub(crate) fn tree_size(
+    path: &Utf8Path,
+    mode: SizeMode,
+    same_filesystem_only: bool,
+) -> io::Result<u64> {
+    let md = fs_metadata(path)?;
+    let device = device_id(path).filter(|_| same_filesystem_only);
+    let mut total = mode.of(&md);
+    if !md.is_dir() {
+        return Ok(total);
+    }
+    let mut seen_links = HashSet::new();
+    let mut dirs = vec![path.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in ls_map(&dir)?.into_values() {
+            if device.is_some() && metadata_device_id(entry.metadata()) != device
+                || is_repeated_link(entry.metadata(), &mut seen_links)
+            {
+                continue;
+            }
+            total += mode.of(entry.metadata());
+            if entry.is_dir() {
+                dirs.push(entry.into_path());
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Whether the file is a hard link to a file that was already counted.
+#[cfg(unix)]
+fn is_repeated_link(md: &Metadata, seen: &mut HashSet<(u64, u64)>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    md.nlink() > 1 && !md.is_dir() && !seen.insert((md.dev(), md.ino()))
+}
+
+#[cfg(not(unix))]
+fn is_repeated_link(_md: &Metadata, _seen: &mut HashSet<(u64, u64)>) -> bool {
+    false
+}
@@ -0,0 +1,131 @@
+use std::{fmt, io};
+
+use camino::Utf8Path;
+
+use crate::{fs::*, Utf8PathExt};
+
+/// A stable text rendering of a directory tree for snapshot testing, e.g. with
+/// `insta::assert_snapshot!(snapshot.to_string())`.
+///
+/// Every entry is rendered on its own line, sorted by path with `/` as separator:
+///
+/// ```text
+/// assets/
+/// assets/logo.png  1234 bytes  9f6c8d1e2a3b4c5d
+/// latest -> assets
+/// readme.md  12 bytes  0c1f2e3d4c5b6a79
+/// ```
+///
+/// Files are rendered with their length and a 64-bit FNV-1a hash of their content.
+/// The hash only serves to detect changes and is stable across platforms and releases.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new().with_file("a/b.txt", "hello");
+/// let snapshot = DirSnapshot::of(dir.path()).unwrap();
+/// assert_eq!(snapshot.to_string(), "a/\na/b.txt  5 bytes  a430d84680aabd0b\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirSnapshot {
+    entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SnapshotEntry {
+    Dir(String),
+    File { path: String, len: u64, hash: u64 },
+    Symlink { path: String, target: String },
+}
+
+impl SnapshotEntry {
+    fn path(&self) -> &str {
+        match self {
+            Self::Dir(path) => path,
+            Self::File { path, .. } | Self::Symlink { path, .. } => path,
+        }
+    }
+}
+
+impl DirSnapshot {
+    /// Read the tree in `dir`. Symlinks are rendered with their target and not followed.
+    pub fn of<P: AsRef<Utf8Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let base = dir.to_path_buf();
+        let mut entries = Vec::new();
+        let ls = dir
+            .ls()
+            .recurse_if(move |rel| !base.join(rel).is_symlink())
+            .relative_paths();
+        for rel in ls.try_iter() {
+            let rel = rel?;
+            let path = dir.join(&rel);
+            let rel = with_slashes(&rel);
+            let md = path.symlink_metadata().map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("Could not read metadata of {path} due to: {e}"),
+                )
+            })?;
+            let entry = if md.is_symlink() {
+                let target = std::fs::read_link(&path).map_err(|e| {
+                    io::Error::new(e.kind(), format!("Could not read link {path} due to: {e}"))
+                })?;
+                SnapshotEntry::Symlink {
+                    path: rel,
+                    target: target.to_string_lossy().replace('\\', "/"),
+                }
+            } else if md.is_dir() {
+                SnapshotEntry::Dir(rel)
+            } else {
+                SnapshotEntry::File {
+                    path: rel,
+                    len: md.len(),
+                    hash: fnv1a(&fs_read(&path)?),
+                }
+            };
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+        Ok(Self { entries })
+    }
+
+    /// The paths of the entries, with directories ending with a `/`.
+    pub fn paths(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries.iter().map(|entry| match entry {
+            SnapshotEntry::Dir(path) => format!("{path}/"),
+            other => other.path().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for DirSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            match entry {
+                SnapshotEntry::Dir(path) => writeln!(f, "{path}/")?,
+                SnapshotEntry::File { path, len, hash } => {
+                    writeln!(f, "{path}  {len} bytes  {hash:016x}")?
+                }
+                SnapshotEntry::Symlink { path, target } => writeln!(f, "{path} -> {target}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn with_slashes(path: &Utf8Path) -> String {
+    path.components()
+        .map(|c| c.as_str())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
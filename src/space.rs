@@ -0,0 +1,63 @@
+use std::io;
+
+use camino::Utf8Path;
+
+use crate::Utf8PathExt;
+
+/// The number of bytes available to the current user on the file system containing
+/// `path`. If `path` doesn't exist, its closest existing ancestor is used.
+pub(crate) fn free_space(path: &Utf8Path) -> io::Result<u64> {
+    let existing = path.deepest_existing().unwrap_or(Utf8Path::new("."));
+    sys_free_space(existing).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not get free space of {path} due to: {e}"),
+        )
+    })
+}
+
+#[cfg(unix)]
+fn sys_free_space(path: &Utf8Path) -> io::Result<u64> {
+    use std::{ffi::CString, mem::MaybeUninit};
+
+    let c_path = CString::new(path.as_str()).map_err(io::Error::other)?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is a valid nul terminated string and `stat` is only read on success.
+    let ret = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn sys_free_space(path: &Utf8Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is nul terminated and the out pointers are valid or null.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn sys_free_space(_path: &Utf8Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Free space is not supported on this platform",
+    ))
+}
@@ -0,0 +1,44 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::task::JoinHandle;
+
+/// A future for a file operation running on tokio's blocking thread pool.
+///
+/// Resolves to the result of the operation. If the blocking task panics or is
+/// cancelled, the join error is turned into an `io::Error`.
+pub struct Spawned<T> {
+    handle: JoinHandle<io::Result<T>>,
+}
+
+impl<T: Send + 'static> Spawned<T> {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: FnOnce() -> io::Result<T> + Send + 'static,
+    {
+        Self {
+            handle: tokio::task::spawn_blocking(f),
+        }
+    }
+
+    /// Abort the task. Note that a blocking task that has already started
+    /// will run to completion.
+    pub fn abort(&self) {
+        self.handle.abort()
+    }
+}
+
+impl<T> Future for Spawned<T> {
+    type Output = io::Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle).poll(cx).map(|res| match res {
+            Ok(res) => res,
+            Err(e) => Err(io::Error::other(format!("Blocking task failed: {e}"))),
+        })
+    }
+}
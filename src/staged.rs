@@ -0,0 +1,140 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::fs_rename, temp::sibling_temp_path, Utf8PathExt};
+
+/// Writes several files so that either all of them are updated or none are.
+///
+/// On [`StagedWrite::commit`] all contents are first written to temporary files next
+/// to their targets. Only when that succeeded are the temporary files renamed over
+/// the targets. If a rename fails, the targets that were already replaced are restored.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let mut staged = StagedWrite::new();
+/// staged.add(dir.join("a.toml"), "a = 1");
+/// staged.add(dir.join("b.toml"), "b = 2");
+/// staged.commit().unwrap();
+/// assert_eq!(dir.join("b.toml").read_string().unwrap(), "b = 2");
+/// ```
+#[derive(Debug, Default)]
+pub struct StagedWrite {
+    files: Vec<(Utf8PathBuf, Vec<u8>)>,
+}
+
+/// A staged file during commit.
+struct Staged<'a> {
+    target: &'a Utf8Path,
+    temp: Utf8PathBuf,
+    backup: Option<Utf8PathBuf>,
+}
+
+impl StagedWrite {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage content for the target path. Staging the same path again replaces the content.
+    pub fn add<P: Into<Utf8PathBuf>, B: Into<Vec<u8>>>(
+        &mut self,
+        path: P,
+        content: B,
+    ) -> &mut Self {
+        let path = path.into();
+        let content = content.into();
+        match self.files.iter_mut().find(|(p, _)| *p == path) {
+            Some(existing) => existing.1 = content,
+            None => self.files.push((path, content)),
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Write all staged files. Parent directories are created as needed.
+    pub fn commit(self) -> io::Result<()> {
+        let mut staged: Vec<Staged> = Vec::with_capacity(self.files.len());
+
+        // Phase 1: write everything to temporary files.
+        for (target, content) in &self.files {
+            let temp = sibling_temp_path(target, "staged");
+            if let Err(e) = write_synced(&temp, content) {
+                let _ = temp.rm();
+                cleanup_temps(&staged);
+                return Err(e);
+            }
+            staged.push(Staged {
+                target,
+                temp,
+                backup: None,
+            });
+        }
+
+        // Phase 2: move the existing targets aside and the temporary files in place.
+        for i in 0..staged.len() {
+            if let Err(e) = swap_in(&mut staged[i]) {
+                rollback(&staged[..i]);
+                if let Some(backup) = &staged[i].backup {
+                    let _ = fs_rename(backup, staged[i].target);
+                }
+                cleanup_temps(&staged[i..]);
+                return Err(e);
+            }
+        }
+
+        for file in &staged {
+            if let Some(backup) = &file.backup {
+                let _ = backup.rm();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_synced(path: &Utf8Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        parent.mkdirs()?;
+    }
+    let mut file = File::create(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not create {path} due to: {e}")))?;
+    file.write_all(content)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| io::Error::new(e.kind(), format!("Could not write to {path} due to: {e}")))
+}
+
+fn swap_in(file: &mut Staged) -> io::Result<()> {
+    if file.target.exists() {
+        let backup = sibling_temp_path(file.target, "backup");
+        fs_rename(file.target, &backup)?;
+        file.backup = Some(backup);
+    }
+    fs_rename(&file.temp, file.target)
+}
+
+/// Restore the targets that were already replaced.
+fn rollback(done: &[Staged]) {
+    for file in done.iter().rev() {
+        match &file.backup {
+            Some(backup) => {
+                let _ = fs_rename(backup, file.target);
+            }
+            None => {
+                let _ = file.target.rm();
+            }
+        }
+    }
+}
+
+fn cleanup_temps(staged: &[Staged]) {
+    for file in staged {
+        let _ = file.temp.rm();
+    }
+}
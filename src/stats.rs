@@ -0,0 +1,120 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    io,
+    time::SystemTime,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    ls::{ls_map, LsEntry},
+    ByteSize, Utf8PathExt,
+};
+
+/// The number of files and their total size, see [`Utf8PathExt::usage_by_extension`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtensionUsage {
+    pub files: u64,
+    pub bytes: ByteSize,
+}
+
+/// A summary of a directory tree, see [`Utf8PathExt::stat_tree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeStats {
+    pub files: u64,
+    /// The number of directories, not counting the directory itself.
+    pub dirs: u64,
+    pub symlinks: u64,
+    /// The total length of the files.
+    pub total_bytes: ByteSize,
+    pub largest_file: Option<(Utf8PathBuf, ByteSize)>,
+    /// The latest modification time of any entry in the tree.
+    pub newest_mtime: Option<SystemTime>,
+}
+
+pub(crate) fn stat_tree(dir: &Utf8Path) -> io::Result<TreeStats> {
+    dir.assert_dir()?;
+    let mut stats = TreeStats::default();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in ls_map(&dir)?.into_values() {
+            let md = entry.metadata();
+            if let Ok(mtime) = md.modified() {
+                stats.newest_mtime = stats.newest_mtime.max(Some(mtime));
+            }
+            if entry.is_symlink() {
+                stats.symlinks += 1;
+            } else if entry.is_dir() {
+                stats.dirs += 1;
+                dirs.push(entry.into_path());
+            } else {
+                let len = ByteSize(md.len());
+                stats.files += 1;
+                stats.total_bytes += len;
+                if stats
+                    .largest_file
+                    .as_ref()
+                    .is_none_or(|(_, max)| len > *max)
+                {
+                    stats.largest_file = Some((entry.into_path(), len));
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
+
+pub(crate) fn usage_by_extension(
+    dir: &Utf8Path,
+) -> io::Result<BTreeMap<Option<String>, ExtensionUsage>> {
+    dir.assert_dir()?;
+    let mut usage: BTreeMap<Option<String>, ExtensionUsage> = BTreeMap::new();
+    for_each_file(dir, |file| {
+        let entry = usage
+            .entry(file.path().extension().map(str::to_string))
+            .or_default();
+        entry.files += 1;
+        entry.bytes += ByteSize(file.metadata().len());
+    })?;
+    Ok(usage)
+}
+
+pub(crate) fn largest_files(dir: &Utf8Path, n: usize) -> io::Result<Vec<(Utf8PathBuf, ByteSize)>> {
+    dir.assert_dir()?;
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    // A min-heap of the largest files seen so far, so the smallest is evicted first.
+    let mut heap: BinaryHeap<Reverse<(u64, Utf8PathBuf)>> = BinaryHeap::with_capacity(n + 1);
+    for_each_file(dir, |file| {
+        let len = file.metadata().len();
+        if heap.len() < n {
+            heap.push(Reverse((len, file.into_path())));
+        } else if heap.peek().is_some_and(|Reverse((min, _))| len > *min) {
+            heap.pop();
+            heap.push(Reverse((len, file.into_path())));
+        }
+    })?;
+    Ok(heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((len, path))| (path, ByteSize(len)))
+        .collect())
+}
+
+/// Call `f` with the regular files below the directory. Like [`stat_tree`], symlinks
+/// are not followed, so nothing outside of the directory is visited.
+fn for_each_file(dir: &Utf8Path, mut f: impl FnMut(LsEntry)) -> io::Result<()> {
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in ls_map(&dir)?.into_values() {
+            if entry.is_dir() {
+                dirs.push(entry.into_path());
+            } else if entry.is_file() {
+                f(entry);
+            }
+        }
+    }
+    Ok(())
+}
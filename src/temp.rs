@@ -0,0 +1,309 @@
+use std::{
+    fs::File,
+    io, mem,
+    ops::Deref,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{fs::fs_rename, Utf8PathExt};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Options for creating a [`Utf8TempDir`] or [`Utf8TempFile`] with a custom name or location.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TempBuilder::new().prefix("build-").suffix(".d").dir().unwrap();
+/// assert!(dir.file_name().unwrap().starts_with("build-"));
+///
+/// let file = TempBuilder::new().suffix(".json").parent(dir.path()).file().unwrap();
+/// assert_eq!(file.parent(), Some(dir.path()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TempBuilder {
+    prefix: String,
+    suffix: String,
+    parent: Option<Utf8PathBuf>,
+}
+
+impl Default for TempBuilder {
+    fn default() -> Self {
+        Self {
+            prefix: ".tmp".to_string(),
+            suffix: String::new(),
+            parent: None,
+        }
+    }
+}
+
+impl TempBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The start of the generated name, `.tmp` by default.
+    pub fn prefix<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// The end of the generated name, e.g. a file extension. Empty by default.
+    pub fn suffix<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.suffix = suffix.into();
+        self
+    }
+
+    /// The directory to create the temporary path in, instead of the system temp
+    /// directory. Use a directory on the same file system as the final location to
+    /// be able to [`Utf8TempFile::persist`] the file there.
+    pub fn parent<P: Into<Utf8PathBuf>>(mut self, parent: P) -> Self {
+        self.parent = Some(parent.into());
+        self
+    }
+
+    /// Create a new, empty directory. On Unix it is only accessible to the current user.
+    pub fn dir(&self) -> io::Result<Utf8TempDir> {
+        let parent = self.parent_or_temp_dir()?;
+        Ok(Utf8TempDir {
+            path: create_unique_dir(&parent, &self.prefix, &self.suffix)?,
+        })
+    }
+
+    /// Create and open a new, empty file. On Unix it is only accessible to the
+    /// current user.
+    pub fn file(&self) -> io::Result<Utf8TempFile> {
+        let parent = self.parent_or_temp_dir()?;
+        let mut options = File::options();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        loop {
+            let path = parent.join(unique_name(&self.prefix, &self.suffix));
+            match options.open(&path) {
+                Ok(file) => {
+                    return Ok(Utf8TempFile {
+                        path,
+                        file: Some(file),
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("Could not create temp file {path} due to: {e}"),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn parent_or_temp_dir(&self) -> io::Result<Utf8PathBuf> {
+        match &self.parent {
+            Some(parent) => Ok(parent.clone()),
+            None => system_temp_dir(),
+        }
+    }
+}
+
+/// A directory that is removed with all its contents when dropped.
+///
+/// Dereferences to its [`Utf8Path`].
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = Utf8TempDir::new().unwrap();
+/// dir.join("a.txt").write("a").unwrap();
+/// let path = dir.to_path_buf();
+/// drop(dir);
+/// assert!(!path.exists());
+/// ```
+#[derive(Debug)]
+pub struct Utf8TempDir {
+    path: Utf8PathBuf,
+}
+
+impl Utf8TempDir {
+    /// Create a new, empty directory in the system temp directory, see [`TempBuilder`]
+    /// for other names and locations.
+    pub fn new() -> io::Result<Self> {
+        TempBuilder::new().dir()
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Keep the directory and return its path.
+    pub fn keep(mut self) -> Utf8PathBuf {
+        mem::take(&mut self.path)
+    }
+
+    /// Remove the directory, returning any error instead of ignoring it as drop does.
+    pub fn close(self) -> io::Result<()> {
+        let path = self.keep();
+        path.rm()
+    }
+}
+
+impl Deref for Utf8TempDir {
+    type Target = Utf8Path;
+
+    fn deref(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl AsRef<Utf8Path> for Utf8TempDir {
+    fn as_ref(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Drop for Utf8TempDir {
+    fn drop(&mut self) {
+        // The path is empty when kept.
+        if !self.path.as_str().is_empty() {
+            let _ = self.path.rm();
+        }
+    }
+}
+
+/// A file that is removed when dropped, unless it is persisted.
+///
+/// Dereferences to its [`Utf8Path`], the open file is available with
+/// [`Utf8TempFile::as_file`].
+///
+/// ```
+/// use camino_fs::*;
+/// use std::io::Write;
+///
+/// let dir = TestDir::new();
+/// let mut file = TempBuilder::new().parent(dir.path()).file().unwrap();
+/// file.as_file_mut().write_all(b"done").unwrap();
+/// let path = file.persist(dir.join("result.txt")).unwrap();
+/// assert_eq!(path.read_string().unwrap(), "done");
+/// ```
+#[derive(Debug)]
+pub struct Utf8TempFile {
+    path: Utf8PathBuf,
+    /// Only taken to close the file before it is removed or renamed, which Windows
+    /// doesn't allow for open files.
+    file: Option<File>,
+}
+
+impl Utf8TempFile {
+    /// Create a new, empty file in the system temp directory, see [`TempBuilder`]
+    /// for other names and locations.
+    pub fn new() -> io::Result<Self> {
+        TempBuilder::new().file()
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    pub fn as_file(&self) -> &File {
+        self.file.as_ref().expect("the file is open until dropped")
+    }
+
+    pub fn as_file_mut(&mut self) -> &mut File {
+        self.file.as_mut().expect("the file is open until dropped")
+    }
+
+    /// Keep the file and return its path. The file is closed.
+    pub fn keep(mut self) -> Utf8PathBuf {
+        self.file = None;
+        mem::take(&mut self.path)
+    }
+
+    /// Move the file to `to`, replacing any file there, and return the new path.
+    ///
+    /// This is a rename, so `to` must be on the same file system as the temp file.
+    /// On failure the temp file is removed.
+    pub fn persist<P: Into<Utf8PathBuf>>(self, to: P) -> io::Result<Utf8PathBuf> {
+        let to = to.into();
+        // Closes the file first.
+        let path = self.keep();
+        if let Err(e) = fs_rename(&path, &to) {
+            let _ = path.rm();
+            return Err(e);
+        }
+        Ok(to)
+    }
+}
+
+impl Deref for Utf8TempFile {
+    type Target = Utf8Path;
+
+    fn deref(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl AsRef<Utf8Path> for Utf8TempFile {
+    fn as_ref(&self) -> &Utf8Path {
+        &self.path
+    }
+}
+
+impl Drop for Utf8TempFile {
+    fn drop(&mut self) {
+        self.file = None;
+        // The path is empty when kept.
+        if !self.path.as_str().is_empty() {
+            let _ = self.path.rm();
+        }
+    }
+}
+
+/// A name that is unique within this process and very unlikely to collide with
+/// names chosen by other processes.
+pub(crate) fn unique_name(prefix: &str, suffix: &str) -> String {
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    format!("{prefix}{}-{count}-{nanos:x}{suffix}", process::id())
+}
+
+/// A hidden path next to `path` for temporary data, e.g. `.file.txt.staged-123-0-1f`.
+pub(crate) fn sibling_temp_path(path: &Utf8Path, tag: &str) -> Utf8PathBuf {
+    let name = path.file_name().unwrap_or("tmp");
+    path.with_file_name(unique_name(&format!(".{name}.{tag}-"), ""))
+}
+
+/// Create a new, empty directory in the system temp directory.
+pub(crate) fn create_temp_dir(prefix: &str) -> io::Result<Utf8PathBuf> {
+    create_unique_dir(&system_temp_dir()?, prefix, "")
+}
+
+fn create_unique_dir(parent: &Utf8Path, prefix: &str, suffix: &str) -> io::Result<Utf8PathBuf> {
+    loop {
+        let path = parent.join(unique_name(prefix, suffix));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&path) {
+            Ok(()) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(io::Error::new(
+                    e.kind(),
+                    format!("Could not create temp directory {path} due to: {e}"),
+                ))
+            }
+        }
+    }
+}
+
+fn system_temp_dir() -> io::Result<Utf8PathBuf> {
+    Utf8PathBuf::from_path_buf(std::env::temp_dir())
+        .map_err(|p| io::Error::other(format!("Temp directory {p:?} is not valid UTF-8")))
+}
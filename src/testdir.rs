@@ -0,0 +1,151 @@
+use std::{io, thread};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{temp::create_temp_dir, DirSnapshot, Utf8PathExt};
+
+/// The environment variable that, when set, keeps all test directories.
+const KEEP_ENV: &str = "CAMINO_FS_KEEP_TEST_DIR";
+
+/// A temporary directory for filesystem-heavy tests, with helpers to build fixtures
+/// and to assert on the resulting tree.
+///
+/// The directory is removed when dropped, unless the test panicked, [`TestDir::keep`]
+/// was called or the `CAMINO_FS_KEEP_TEST_DIR` environment variable is set. A kept
+/// directory is printed to stderr so it can be inspected.
+///
+/// As it is meant for tests, the helpers panic instead of returning errors.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new()
+///     .with_file("src/main.rs", "fn main() {}")
+///     .with_dir("target");
+/// dir.join("src/main.rs").cp(dir.join("src/copy.rs")).unwrap();
+///
+/// dir.assert_file("src/copy.rs", "fn main() {}");
+/// dir.assert_tree(&["src/", "src/copy.rs", "src/main.rs", "target/"]);
+/// ```
+#[derive(Debug)]
+pub struct TestDir {
+    path: Utf8PathBuf,
+    keep: bool,
+}
+
+impl TestDir {
+    /// Create a new, empty directory in the system temp directory.
+    pub fn new() -> Self {
+        Self::try_new().expect("Could not create test directory")
+    }
+
+    pub fn try_new() -> io::Result<Self> {
+        Ok(Self {
+            path: create_temp_dir("camino-fs-test-")?,
+            keep: false,
+        })
+    }
+
+    /// Keep the directory after the test.
+    pub fn keep(mut self) -> Self {
+        self.keep = true;
+        self
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// The path relative to the test directory.
+    pub fn join<P: AsRef<Utf8Path>>(&self, rel: P) -> Utf8PathBuf {
+        self.path.join(rel)
+    }
+
+    /// Add a file, creating its parent directories.
+    pub fn with_file<P: AsRef<Utf8Path>, B: AsRef<[u8]>>(self, rel: P, contents: B) -> Self {
+        let path = self.join(rel);
+        path.write(contents)
+            .unwrap_or_else(|e| panic!("Could not create fixture {path}: {e}"));
+        self
+    }
+
+    /// Add a directory, creating its parent directories.
+    pub fn with_dir<P: AsRef<Utf8Path>>(self, rel: P) -> Self {
+        let path = self.join(rel);
+        path.mkdirs()
+            .unwrap_or_else(|e| panic!("Could not create fixture {path}: {e}"));
+        self
+    }
+
+    /// Copy a file or directory into the test directory at `rel`.
+    pub fn with_copy<P: AsRef<Utf8Path>, Q: AsRef<Utf8Path>>(self, from: P, rel: Q) -> Self {
+        let (from, path) = (from.as_ref(), self.join(rel));
+        from.cp(&path)
+            .unwrap_or_else(|e| panic!("Could not copy fixture {from} to {path}: {e}"));
+        self
+    }
+
+    /// A snapshot of the directory, see [`DirSnapshot`].
+    pub fn snapshot(&self) -> DirSnapshot {
+        DirSnapshot::of(&self.path)
+            .unwrap_or_else(|e| panic!("Could not read tree of {}: {e}", self.path))
+    }
+
+    /// All entries in the directory, relative to it, sorted and with `/` as separator.
+    /// Directories end with a `/`.
+    pub fn tree(&self) -> Vec<String> {
+        self.snapshot().paths().collect()
+    }
+
+    /// Assert that the directory contains exactly the given entries, see [`TestDir::tree`].
+    #[track_caller]
+    pub fn assert_tree(&self, expected: &[&str]) {
+        let mut expected: Vec<&str> = expected.to_vec();
+        expected.sort();
+        assert_eq!(self.tree(), expected, "Unexpected tree in {}", self.path);
+    }
+
+    /// Assert that the file exists and has the given contents.
+    #[track_caller]
+    pub fn assert_file<P: AsRef<Utf8Path>, B: AsRef<[u8]>>(&self, rel: P, expected: B) {
+        let path = self.join(rel);
+        let contents = path
+            .read_bytes()
+            .unwrap_or_else(|e| panic!("Could not read {path}: {e}"));
+        if contents != expected.as_ref() {
+            panic!(
+                "Unexpected contents of {path}:\n{}\nExpected:\n{}",
+                String::from_utf8_lossy(&contents),
+                String::from_utf8_lossy(expected.as_ref())
+            );
+        }
+    }
+
+    #[track_caller]
+    pub fn assert_exists<P: AsRef<Utf8Path>>(&self, rel: P) {
+        let path = self.join(rel);
+        assert!(path.exists(), "Path {path} does not exist");
+    }
+
+    #[track_caller]
+    pub fn assert_missing<P: AsRef<Utf8Path>>(&self, rel: P) {
+        let path = self.join(rel);
+        assert!(!path.exists(), "Path {path} exists");
+    }
+}
+
+impl Default for TestDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        if self.keep || thread::panicking() || std::env::var_os(KEEP_ENV).is_some() {
+            eprintln!("Keeping test directory {}", self.path);
+        } else {
+            let _ = self.path.rm();
+        }
+    }
+}
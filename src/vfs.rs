@@ -0,0 +1,381 @@
+use std::{
+    fs::{FileTimes, Metadata, Permissions},
+    io,
+    sync::Mutex,
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::{
+    flags,
+    fs::*,
+    glob::Glob,
+    io_stats::{count, Counter},
+    link, Utf8PathExt,
+};
+
+/// The file system operations used by code that wants to be testable against
+/// something else than the real file system, see [`StdFs`] and [`FaultyFs`].
+///
+/// [`Utf8PathExt::write_in`], [`Utf8PathExt::cp_in`] and [`Utf8PathExt::rm_in`] run
+/// the same implementation as [`Utf8PathExt::write`], [`Utf8PathExt::cp_with`] and
+/// [`Utf8PathExt::rm`] on top of these, which use [`StdFs`]. The owner, ACLs and
+/// extended attributes preserved by [`CpOptions`](crate::CpOptions), and its free
+/// space check, always use the real file system.
+///
+/// The metadata errors keep their kind, so that a missing path can be told apart.
+pub trait Vfs {
+    fn read(&self, path: &Utf8Path) -> io::Result<Vec<u8>>;
+
+    /// Write the file, creating it if it does not exist and replacing its content if it does.
+    fn write(&self, path: &Utf8Path, buf: &[u8]) -> io::Result<()>;
+
+    fn create_dir(&self, path: &Utf8Path) -> io::Result<()>;
+
+    fn create_dir_all(&self, path: &Utf8Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Utf8Path) -> io::Result<()>;
+
+    /// Remove an empty directory.
+    fn remove_dir(&self, path: &Utf8Path) -> io::Result<()>;
+
+    fn remove_dir_all(&self, path: &Utf8Path) -> io::Result<()>;
+
+    fn rename(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()>;
+
+    fn copy(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()>;
+
+    /// Recreate the symlink or junction at `from` as `to`, pointing to the same target.
+    fn copy_link(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()>;
+
+    /// The paths of the entries in the directory, in no particular order.
+    fn read_dir(&self, path: &Utf8Path) -> io::Result<Vec<Utf8PathBuf>>;
+
+    fn metadata(&self, path: &Utf8Path) -> io::Result<Metadata>;
+
+    /// The metadata of the path without following a symlink.
+    fn symlink_metadata(&self, path: &Utf8Path) -> io::Result<Metadata>;
+
+    fn set_times(&self, path: &Utf8Path, times: FileTimes) -> io::Result<()>;
+
+    fn set_permissions(&self, path: &Utf8Path, perm: Permissions) -> io::Result<()>;
+
+    fn exists(&self, path: &Utf8Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+/// The real file system, using the same functions as [`Utf8PathExt`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFs;
+
+impl Vfs for StdFs {
+    fn read(&self, path: &Utf8Path) -> io::Result<Vec<u8>> {
+        fs_read(path)
+    }
+
+    fn write(&self, path: &Utf8Path, buf: &[u8]) -> io::Result<()> {
+        fs_write(path, buf)
+    }
+
+    fn create_dir(&self, path: &Utf8Path) -> io::Result<()> {
+        fs_create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Utf8Path) -> io::Result<()> {
+        fs_create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Utf8Path) -> io::Result<()> {
+        fs_remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Utf8Path) -> io::Result<()> {
+        fs_remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Utf8Path) -> io::Result<()> {
+        fs_remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        fs_rename(from, to)
+    }
+
+    fn copy(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        fs_copy(from, to)
+    }
+
+    fn copy_link(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        link::copy_link(from, to)
+    }
+
+    fn read_dir(&self, path: &Utf8Path) -> io::Result<Vec<Utf8PathBuf>> {
+        path.ls().try_iter().collect()
+    }
+
+    fn metadata(&self, path: &Utf8Path) -> io::Result<Metadata> {
+        count(Counter::MetadataCalls, 1);
+        path.metadata().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not read metadata of {path} due to: {e}"),
+            )
+        })
+    }
+
+    fn symlink_metadata(&self, path: &Utf8Path) -> io::Result<Metadata> {
+        count(Counter::MetadataCalls, 1);
+        path.symlink_metadata().map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Could not read metadata of {path} due to: {e}"),
+            )
+        })
+    }
+
+    fn set_times(&self, path: &Utf8Path, times: FileTimes) -> io::Result<()> {
+        fs_set_times(path, times)
+    }
+
+    fn set_permissions(&self, path: &Utf8Path, perm: Permissions) -> io::Result<()> {
+        fs_set_permissions(path, perm)
+    }
+}
+
+/// A [`Vfs`] decorator that injects errors, to test how code handles them.
+///
+/// ```
+/// use camino_fs::*;
+///
+/// let dir = TestDir::new();
+/// let fs = FaultyFs::new(StdFs)
+///     .storage_full_after(8)
+///     .deny("**/*.key")
+///     .unwrap();
+///
+/// dir.join("a.txt").write_in(&fs, "12345").unwrap();
+/// let options = CpOptions::new();
+/// let err = dir.join("a.txt").cp_in(&fs, dir.join("b.txt"), &options).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::StorageFull);
+/// let err = dir.join("secret/id.key").rm_in(&fs).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+/// ```
+#[derive(Debug)]
+pub struct FaultyFs<F> {
+    inner: F,
+    storage_full_after: Option<u64>,
+    deny: Vec<Glob>,
+    interrupt_probability: f64,
+    state: Mutex<FaultState>,
+}
+
+#[derive(Debug)]
+struct FaultState {
+    written: u64,
+    rng: u64,
+}
+
+impl<F: Vfs> FaultyFs<F> {
+    /// Wrap the file system without injecting any errors yet.
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            storage_full_after: None,
+            deny: Vec::new(),
+            interrupt_probability: 0.0,
+            state: Mutex::new(FaultState {
+                written: 0,
+                rng: 0x9E3779B97F4A7C15,
+            }),
+        }
+    }
+
+    /// Fail writes with a `StorageFull` error once `bytes` have been written in total.
+    /// A write that would exceed the limit fails without writing anything.
+    pub fn storage_full_after(mut self, bytes: u64) -> Self {
+        self.storage_full_after = Some(bytes);
+        self
+    }
+
+    /// Fail all operations on paths that match the glob with a `PermissionDenied`
+    /// error. `*` doesn't match `/`, while `**` does.
+    pub fn deny(mut self, glob: &str) -> io::Result<Self> {
+        self.deny.push(Glob::new(glob)?);
+        Ok(self)
+    }
+
+    /// Fail operations at random with an `Interrupted` error. The `seed` makes the
+    /// sequence of failures reproducible.
+    pub fn interrupt_randomly(mut self, probability: f64, seed: u64) -> Self {
+        self.interrupt_probability = probability;
+        // Xorshift gets stuck at zero.
+        self.state.get_mut().unwrap().rng = seed.max(1);
+        self
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// The number of bytes written so far, as counted for [`FaultyFs::storage_full_after`].
+    pub fn written(&self) -> u64 {
+        self.state.lock().unwrap().written
+    }
+
+    /// Count `len` more bytes as written, failing if that exceeds the limit. The
+    /// lock is only held for the counting, so that the I/O can run in parallel.
+    fn reserve(&self, op: &str, path: &Utf8Path, len: u64) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(limit) = self.storage_full_after {
+            if state.written + len > limit {
+                return Err(io::Error::new(
+                    io::ErrorKind::StorageFull,
+                    format!("Could not {op} {path} due to: injected storage full"),
+                ));
+            }
+        }
+        state.written += len;
+        Ok(())
+    }
+
+    /// Undo [`FaultyFs::reserve`] after the I/O failed.
+    fn unreserve(&self, len: u64) {
+        self.state.lock().unwrap().written -= len;
+    }
+
+    fn check(&self, op: &str, paths: &[&Utf8Path]) -> io::Result<()> {
+        for path in paths {
+            if self.deny.iter().any(|glob| glob.is_match(path.as_str())) {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("Could not {op} {path} due to: injected permission denied"),
+                ));
+            }
+        }
+        if self.interrupt_probability > 0.0 {
+            let mut state = self.state.lock().unwrap();
+            state.rng ^= state.rng << 13;
+            state.rng ^= state.rng >> 7;
+            state.rng ^= state.rng << 17;
+            let sample = (state.rng >> 11) as f64 / (1u64 << 53) as f64;
+            if sample < self.interrupt_probability {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    format!("Could not {op} {} due to: injected interrupt", paths[0]),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<F: Vfs> Vfs for FaultyFs<F> {
+    fn read(&self, path: &Utf8Path) -> io::Result<Vec<u8>> {
+        self.check("read", &[path])?;
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Utf8Path, buf: &[u8]) -> io::Result<()> {
+        self.check("write", &[path])?;
+        self.reserve("write", path, buf.len() as u64)?;
+        self.inner
+            .write(path, buf)
+            .inspect_err(|_| self.unreserve(buf.len() as u64))
+    }
+
+    fn create_dir(&self, path: &Utf8Path) -> io::Result<()> {
+        self.check("create", &[path])?;
+        self.inner.create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Utf8Path) -> io::Result<()> {
+        self.check("create", &[path])?;
+        self.inner.create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Utf8Path) -> io::Result<()> {
+        self.check("remove", &[path])?;
+        self.inner.remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Utf8Path) -> io::Result<()> {
+        self.check("remove", &[path])?;
+        self.inner.remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Utf8Path) -> io::Result<()> {
+        self.check("remove", &[path])?;
+        self.inner.remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        self.check("rename", &[from, to])?;
+        self.inner.rename(from, to)
+    }
+
+    fn copy(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        self.check("copy", &[from, to])?;
+        let len = self.inner.metadata(from)?.len();
+        self.reserve("copy", to, len)?;
+        self.inner
+            .copy(from, to)
+            .inspect_err(|_| self.unreserve(len))
+    }
+
+    fn copy_link(&self, from: &Utf8Path, to: &Utf8Path) -> io::Result<()> {
+        self.check("copy", &[from, to])?;
+        self.inner.copy_link(from, to)
+    }
+
+    fn read_dir(&self, path: &Utf8Path) -> io::Result<Vec<Utf8PathBuf>> {
+        self.check("read directory", &[path])?;
+        self.inner.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Utf8Path) -> io::Result<Metadata> {
+        self.check("read metadata of", &[path])?;
+        self.inner.metadata(path)
+    }
+
+    fn symlink_metadata(&self, path: &Utf8Path) -> io::Result<Metadata> {
+        self.check("read metadata of", &[path])?;
+        self.inner.symlink_metadata(path)
+    }
+
+    fn set_times(&self, path: &Utf8Path, times: FileTimes) -> io::Result<()> {
+        self.check("set times of", &[path])?;
+        self.inner.set_times(path, times)
+    }
+
+    fn set_permissions(&self, path: &Utf8Path, perm: Permissions) -> io::Result<()> {
+        self.check("set permissions of", &[path])?;
+        self.inner.set_permissions(path, perm)
+    }
+}
+
+/// Write the file like [`Utf8PathExt::write`], creating the parent directories.
+pub(crate) fn write(fs: &dyn Vfs, path: &Utf8Path, buf: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_str().is_empty()) {
+        fs.create_dir_all(parent)?;
+    }
+    fs.write(path, buf)
+}
+
+/// Remove the file, directory or link like [`Utf8PathExt::rm`].
+pub(crate) fn rm(fs: &dyn Vfs, path: &Utf8Path) -> io::Result<()> {
+    let md = match fs.symlink_metadata(path) {
+        Ok(md) => md,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    // Junctions are reported as symlinks as well.
+    let result = if md.is_symlink() {
+        link::remove_link(fs, path, &md)
+    } else if md.is_dir() {
+        fs.remove_dir_all(path)
+    } else {
+        fs.remove_file(path)
+    };
+    result.map_err(|e| flags::explain_rm_error(path, e))
+}
@@ -0,0 +1,235 @@
+use std::{
+    ffi::{CStr, CString},
+    io,
+};
+
+use camino::Utf8Path;
+
+fn c_string(s: &str) -> io::Result<CString> {
+    CString::new(s).map_err(io::Error::other)
+}
+
+/// The error of a missing attribute, which Linux calls `ENODATA`.
+#[cfg(target_os = "linux")]
+const ENOATTR: i32 = libc::ENODATA;
+#[cfg(target_os = "macos")]
+const ENOATTR: i32 = libc::ENOATTR;
+
+/// The value of the extended attribute, or `None` if it isn't set or the file
+/// system doesn't support extended attributes.
+pub(crate) fn get(path: &Utf8Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    let (c_path, c_name) = (c_string(path.as_str())?, c_string(name)?);
+    let absent = |e: &io::Error| matches!(e.raw_os_error(), Some(ENOATTR | libc::ENOTSUP));
+    read_sized(|buf| sys::getxattr(&c_path, &c_name, buf))
+        .map(Some)
+        .or_else(|e| if absent(&e) { Ok(None) } else { Err(e) })
+}
+
+/// Set the extended attribute, or remove it when `value` is `None`.
+pub(crate) fn set(path: &Utf8Path, name: &str, value: Option<&[u8]>) -> io::Result<()> {
+    let (c_path, c_name) = (c_string(path.as_str())?, c_string(name)?);
+    let ret = match value {
+        Some(value) => sys::setxattr(&c_path, &c_name, value),
+        None => sys::removexattr(&c_path, &c_name),
+    };
+    if ret == 0 {
+        return Ok(());
+    }
+    let e = io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(ENOATTR | libc::ENOTSUP) if value.is_none() => Ok(()),
+        _ => Err(e),
+    }
+}
+
+/// The names of the extended attributes, empty if the file system doesn't support them.
+pub(crate) fn list(path: &Utf8Path) -> io::Result<Vec<String>> {
+    let c_path = c_string(path.as_str())?;
+    let names = match read_sized(|buf| sys::listxattr(&c_path, buf)) {
+        Ok(names) => names,
+        Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(names
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+/// Call `read` first without a buffer to get the length and then with a buffer of
+/// that length, retrying if the value grew in between.
+fn read_sized<F>(read: F) -> io::Result<Vec<u8>>
+where
+    F: Fn(Option<&mut [u8]>) -> isize,
+{
+    loop {
+        let len = read(None);
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut value = vec![0u8; len as usize];
+        let len = read(Some(&mut value));
+        if len < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(e);
+        }
+        value.truncate(len as usize);
+        return Ok(value);
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn security_context(path: &Utf8Path) -> io::Result<Option<String>> {
+    let value = get(path, "security.selinux").map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read security context of {path} due to: {e}"),
+        )
+    })?;
+    Ok(value.map(|value| {
+        // The kernel includes the terminating nul.
+        let value = CStr::from_bytes_until_nul(&value).map_or(&value[..], CStr::to_bytes);
+        String::from_utf8_lossy(value).into_owned()
+    }))
+}
+
+/// Copy the extended attributes in the `security` namespace from `src` to `dest`.
+#[cfg(target_os = "linux")]
+pub(crate) fn copy_security(src: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    copy_matching(src, dest, "security", |name| name.starts_with("security."))
+}
+
+/// Copy the extended attributes that an unprivileged process may set, which are
+/// those in the `user` namespace on Linux and all of them on macOS.
+#[cfg(feature = "xattr")]
+pub(crate) fn copy_user(src: &Utf8Path, dest: &Utf8Path) -> io::Result<()> {
+    copy_matching(src, dest, "extended", |name| {
+        cfg!(target_os = "macos") || name.starts_with("user.")
+    })
+}
+
+fn copy_matching<F>(src: &Utf8Path, dest: &Utf8Path, what: &str, filter: F) -> io::Result<()>
+where
+    F: Fn(&str) -> bool,
+{
+    let err = |e: io::Error| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not copy {what} attributes of {src} to {dest} due to: {e}"),
+        )
+    };
+    for name in list(src).map_err(err)? {
+        if !filter(&name) {
+            continue;
+        }
+        if let Some(value) = get(src, &name).map_err(err)? {
+            set(dest, &name, Some(&value)).map_err(err)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "xattr")]
+pub(crate) fn xattr_get(path: &Utf8Path, name: &str) -> io::Result<Option<Vec<u8>>> {
+    get(path, name).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not read extended attribute {name} of {path} due to: {e}"),
+        )
+    })
+}
+
+#[cfg(feature = "xattr")]
+pub(crate) fn xattr_set(path: &Utf8Path, name: &str, value: Option<&[u8]>) -> io::Result<()> {
+    let action = if value.is_some() { "set" } else { "remove" };
+    set(path, name, value).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not {action} extended attribute {name} of {path} due to: {e}"),
+        )
+    })
+}
+
+#[cfg(feature = "xattr")]
+pub(crate) fn xattr_list(path: &Utf8Path) -> io::Result<Vec<String>> {
+    list(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Could not list extended attributes of {path} due to: {e}"),
+        )
+    })
+}
+
+/// The calls that differ between Linux and macOS, where the latter takes a position
+/// for resource forks and options such as not following symlinks.
+mod sys {
+    use std::{ffi::CStr, ptr};
+
+    /// The pointer and length of the buffer, or null to query the length.
+    fn raw(buf: Option<&mut [u8]>) -> (*mut libc::c_void, usize) {
+        buf.map_or((ptr::null_mut(), 0), |buf| {
+            (buf.as_mut_ptr().cast(), buf.len())
+        })
+    }
+
+    pub(super) fn getxattr(path: &CStr, name: &CStr, buf: Option<&mut [u8]>) -> isize {
+        let (buf, len) = raw(buf);
+        // SAFETY: both strings are nul terminated and the buffer is valid for `len` bytes
+        // or null with a zero length to query the length.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buf, len)
+        }
+        // SAFETY: as above.
+        #[cfg(target_os = "macos")]
+        unsafe {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), buf, len, 0, 0)
+        }
+    }
+
+    pub(super) fn setxattr(path: &CStr, name: &CStr, value: &[u8]) -> i32 {
+        let (value, len) = (value.as_ptr().cast(), value.len());
+        // SAFETY: both strings are nul terminated and the value is valid for `len` bytes.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::setxattr(path.as_ptr(), name.as_ptr(), value, len, 0)
+        }
+        // SAFETY: as above.
+        #[cfg(target_os = "macos")]
+        unsafe {
+            libc::setxattr(path.as_ptr(), name.as_ptr(), value, len, 0, 0)
+        }
+    }
+
+    pub(super) fn removexattr(path: &CStr, name: &CStr) -> i32 {
+        // SAFETY: both strings are nul terminated.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::removexattr(path.as_ptr(), name.as_ptr())
+        }
+        // SAFETY: as above.
+        #[cfg(target_os = "macos")]
+        unsafe {
+            libc::removexattr(path.as_ptr(), name.as_ptr(), 0)
+        }
+    }
+
+    pub(super) fn listxattr(path: &CStr, buf: Option<&mut [u8]>) -> isize {
+        let (buf, len) = raw(buf);
+        // SAFETY: the string is nul terminated and the buffer is valid for `len` bytes
+        // or null with a zero length to query the length.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::listxattr(path.as_ptr(), buf.cast(), len)
+        }
+        // SAFETY: as above.
+        #[cfg(target_os = "macos")]
+        unsafe {
+            libc::listxattr(path.as_ptr(), buf.cast(), len, 0)
+        }
+    }
+}